@@ -0,0 +1,48 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{ AccountId, Balance };
+
+use crate::types::{ Timestamp, WrappedBalance, WrappedDuration, WrappedTimestamp };
+
+/// Allowance to spend treasury funds without a vote, up to `cap` per `period`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Budget {
+    /// Token contract for fungible token budgets, NEAR if `None`.
+    pub token: Option<AccountId>,
+    pub cap: WrappedBalance,
+    pub period: WrappedDuration,
+    pub period_start: WrappedTimestamp,
+    pub spent_in_period: WrappedBalance,
+}
+
+impl Budget {
+    /// Moves the budget to the period containing `timestamp`, resetting the spent amount.
+    pub fn roll_period(&mut self, timestamp: Timestamp) {
+        let elapsed = timestamp - self.period_start.0;
+        if elapsed >= self.period.0 {
+            self.period_start = (self.period_start.0 + elapsed / self.period.0 * self.period.0).into();
+            self.spent_in_period = 0.into();
+        }
+    }
+
+    pub fn remaining(&self) -> Balance {
+        self.cap.0 - self.spent_in_period.0
+    }
+}
+
+/// Audit record of a payment made from a budget.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BudgetSpend {
+    pub spender: AccountId,
+    pub target: AccountId,
+    pub token: Option<AccountId>,
+    pub amount: WrappedBalance,
+    pub memo: String,
+    pub timestamp: WrappedTimestamp,
+    /// Start of the budget period the amount was counted in.
+    pub period_start: WrappedTimestamp,
+    /// Set when the token transfer failed and the amount was returned to the budget.
+    pub failed: bool,
+}
@@ -0,0 +1,21 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::AccountId;
+
+/// Delegation of a council member's vote to another council member.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Delegation {
+    pub delegate: AccountId,
+    /// Proposal kinds the delegation is limited to, all kinds if `None`.
+    pub kinds: Option<Vec<String>>,
+}
+
+impl Delegation {
+    pub fn applies_to(&self, kind: &str) -> bool {
+        match self.kinds {
+            Some(ref kinds) => kinds.iter().any(|k| k == kind),
+            None => true,
+        }
+    }
+}
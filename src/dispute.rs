@@ -0,0 +1,15 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::AccountId;
+
+use crate::types::{ WrappedBalance, WrappedTimestamp };
+
+/// Challenge of a passed market resolution, which sends it back to a vote with a higher threshold.
+/// The bonds of both sides go to the winning side.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Dispute {
+    pub disputer: AccountId,
+    pub bond: WrappedBalance,
+    pub disputed_at: WrappedTimestamp,
+}
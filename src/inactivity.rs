@@ -0,0 +1,35 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::json_types::{U64};
+
+/// Defines when a council member is considered inactive.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InactivityPolicy {
+    /// Number of most recent closed proposals to look at.
+    pub window: U64,
+    /// Minimum share of those proposals a member must vote on.
+    pub min_participation: ParticipationRatio,
+}
+
+/// Share of proposals, `numerator / denominator`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ParticipationRatio {
+    pub numerator: U64,
+    pub denominator: U64,
+}
+
+impl InactivityPolicy {
+    pub fn is_inactive(&self, voted: u64, total: u64) -> bool {
+        voted * self.min_participation.denominator.0 < self.min_participation.numerator.0 * total
+    }
+}
+
+/// Participation of a council member over a number of closed proposals.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Participation {
+    pub voted: U64,
+    pub total: U64,
+}
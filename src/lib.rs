@@ -4,46 +4,171 @@ use std::collections::HashMap;
 use near_sdk::{ ext_contract, AccountId, Balance, Gas, env, near_bindgen, Promise, PromiseOrValue, PromiseResult};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::{UnorderedSet, Vector, UnorderedMap};
-use near_sdk::json_types::{U64, U128};
+use near_sdk::json_types::{U64, U128, Base58PublicKey, Base64VecU8};
 
 // TODO: rewrite to same type of imports as from l19, if possible
 use crate::utils::{ to_yocto };
-pub use crate::types::{ NumOrRatio, Vote };
+pub use crate::types::{ NumOrRatio, Vote, VoteResult, VotingMode };
 
 mod proposal_status;
 mod proposal;
 mod policy_item;
+mod inactivity;
+mod membership;
+mod delegation;
+mod treasury;
+mod stream;
+mod budget;
+mod dispute;
 mod types;
 mod utils;
 
 use policy_item::{ PolicyItem };
-pub use proposal::{ Proposal, ProposalInput, ProposalKind };
+pub use inactivity::{ InactivityPolicy, Participation, ParticipationRatio };
+pub use membership::{ PendingStake, CouncilBounds, StakeCheckpoint };
+pub use delegation::{ Delegation };
+pub use treasury::{ TreasurySummary };
+pub use stream::{ Stream, StreamBalance };
+pub use budget::{ Budget, BudgetSpend };
+pub use dispute::{ Dispute };
+pub use proposal::{ Proposal, ProposalInput, ProposalKind, ActionResult, Amendment };
 pub use proposal_status::{ ProposalStatus };
-use types::{ Duration, WrappedBalance, WrappedDuration };
+use types::{ Duration, Timestamp, WrappedBalance, WrappedDuration };
 
 #[global_allocator]
 static ALLOC: near_sdk::wee_alloc::WeeAlloc<'_> = near_sdk::wee_alloc::WeeAlloc::INIT;
 
 const MAX_DESCRIPTION_LENGTH: usize = 280;
+/// 30 days in nanoseconds.
+const MAX_GRACE_PERIOD: Duration = 30 * 24 * 60 * 60 * 1_000_000_000;
+/// 30 days in nanoseconds.
+const DEFAULT_EXECUTION_WINDOW: Duration = 30 * 24 * 60 * 60 * 1_000_000_000;
+/// 1 day in nanoseconds.
+const MIN_EXECUTION_WINDOW: Duration = 24 * 60 * 60 * 1_000_000_000;
+const MAX_INACTIVITY_WINDOW: u64 = 100;
+/// Most proposals looked at when counting participation, proposals that don't count are skipped.
+const MAX_PARTICIPATION_SCAN: u64 = 2 * MAX_INACTIVITY_WINDOW;
+const DEFAULT_MIN_COUNCIL: u64 = 1;
+const DEFAULT_MAX_COUNCIL: u64 = 100;
 const RESOLUTION_GAS: u64 = 5_000_000_000_000;
+/// Storage staking cost per byte, 1 NEAR per 100kb.
+const STORAGE_PRICE_PER_BYTE: Balance = 10_000_000_000_000_000_000;
+const FT_TRANSFER_GAS: u64 = 10_000_000_000_000;
+const ONE_YOCTO: Balance = 1;
+/// `ft_on_transfer` message that deposits the staking token into the treasury instead of staking it.
+const TREASURY_DEPOSIT_MSG: &str = "deposit";
+const MAX_MEMO_LENGTH: usize = 280;
+const MAX_LINK_LENGTH: usize = 512;
+const MAX_VOTE_KEYS: usize = 5;
+/// 10 NEAR.
+const DEFAULT_REPORTER_BOND: Balance = 10_000_000_000_000_000_000_000_000;
+/// 1 NEAR.
+const DEFAULT_REPORTER_REWARD: Balance = 1_000_000_000_000_000_000_000_000;
+/// 10 NEAR.
+const DEFAULT_DISPUTE_BOND: Balance = 10_000_000_000_000_000_000_000_000;
+const MAX_ACTIONS: usize = 5;
+const MAX_DEPENDENCIES: usize = 5;
+/// Gas for each step of a multi-action proposal, enough to start the next external action.
+const ACTION_CALLBACK_GAS: u64 = 30_000_000_000_000;
+/// Gas kept by `on_multi_action` itself, the rest is passed on to the next step.
+const ON_MULTI_ACTION_GAS: u64 = 10_000_000_000_000;
+/// Stake history kept per account, older checkpoints are dropped.
+const MAX_STAKE_CHECKPOINTS: usize = 50;
 
 const RESOLUTE_POLICY : PolicyItem = PolicyItem {
     max_amount: U128(0),
     votes: NumOrRatio::Number(4),
 };
 
+/// Supermajority of the council needed to uphold a disputed resolution, never fewer votes than
+/// `RESOLUTE_POLICY` needs to pass it in the first place.
+const DISPUTE_POLICY : PolicyItem = PolicyItem {
+    max_amount: U128(0),
+    votes: NumOrRatio::Ratio(2, 3),
+};
+
+/// Storage cost of the off-chain metadata of a proposal, paid on top of the bond.
+fn metadata_storage_cost(proposal: &ProposalInput) -> Balance {
+    proposal.metadata_len() as Balance * STORAGE_PRICE_PER_BYTE
+}
+
 #[near_bindgen]
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct FluxDAO {
     purpose: String,
     bond: Balance,
     vote_period: Duration,
+    /// Time between the outcome of a vote being decided and the proposal being executable.
     grace_period: Duration,
     policy: PolicyItem,
     council: UnorderedSet<AccountId>,
     proposals: Vector<Proposal>,
     last_voted: UnorderedMap<AccountId, u64>,
-    protocol_address: AccountId
+    protocol_address: AccountId,
+    /// Number of proposals that existed when each council member joined.
+    council_since: UnorderedMap<AccountId, u64>,
+    inactivity_policy: InactivityPolicy,
+    min_council: u64,
+    max_council: u64,
+    /// Stake a new council member has to deposit to take their seat, 0 disables it.
+    council_stake: Balance,
+    /// How long a former member has to wait before withdrawing their stake.
+    stake_cooldown: Duration,
+    /// Accounts invited by a `NewCouncil` proposal, with the stake they have to deposit.
+    invitations: UnorderedMap<AccountId, Balance>,
+    member_stakes: UnorderedMap<AccountId, Balance>,
+    pending_stakes: UnorderedMap<AccountId, PendingStake>,
+    /// Voting mode new proposals are created with, `ResoluteMarket` is always voted on by the council.
+    voting_mode: VotingMode,
+    /// Governance token that can be staked for token weighted voting power.
+    staking_token: Option<AccountId>,
+    unbonding_period: Duration,
+    stakes: UnorderedMap<AccountId, Balance>,
+    total_staked: Balance,
+    unbonding: UnorderedMap<AccountId, PendingStake>,
+    /// Timestamp until which an account can't unstake, the latest end of a vote period it voted in.
+    vote_locks: UnorderedMap<AccountId, Timestamp>,
+    delegations: UnorderedMap<AccountId, Delegation>,
+    /// Fungible token balances held by the treasury, per token contract.
+    ft_balances: UnorderedMap<AccountId, Balance>,
+    /// Bonds of proposals that are not finalized yet.
+    locked_bonds: Balance,
+    /// Council membership stakes held by the DAO, including stakes in cooldown.
+    locked_stakes: Balance,
+    streams: Vector<Stream>,
+    /// NEAR committed to streams and not withdrawn yet.
+    locked_streams: Balance,
+    budgets: UnorderedMap<AccountId, Budget>,
+    budget_spends: Vector<BudgetSpend>,
+    /// Delay between a proposal passing and its execution, per proposal kind.
+    timelocks: UnorderedMap<String, Duration>,
+    /// Accounts that can veto queued proposals.
+    guardians: UnorderedSet<AccountId>,
+    /// Grace periods overriding the default one, per proposal kind.
+    kind_grace_periods: UnorderedMap<String, Duration>,
+    /// How long a passed proposal can be executed once its grace period or timelock ended, 0 disables it.
+    execution_window: Duration,
+    /// Whether the bond of an expired proposal goes back to the proposer or stays in the treasury.
+    refund_expired_bonds: bool,
+    /// Reveal window after the vote period, per proposal kind using commit-reveal voting.
+    reveal_periods: UnorderedMap<String, Duration>,
+    /// ed25519 keys council members sign relayed votes with.
+    vote_keys: UnorderedMap<AccountId, Vec<Base58PublicKey>>,
+    /// Last nonce used in a relayed vote, per account.
+    vote_nonces: UnorderedMap<AccountId, u64>,
+    /// Bond of `ResoluteMarket` proposals created by accounts outside the council.
+    reporter_bond: Balance,
+    /// Paid to a reporter whose resolution was executed.
+    reporter_reward: Balance,
+    /// Time after a market resolution passed during which it can be disputed, 0 disables disputes.
+    dispute_window: Duration,
+    dispute_bond: Balance,
+    /// `ResoluteMarket` proposals per market id.
+    resolutions: UnorderedMap<u64, Vec<u64>>,
+    /// Resolution proposal that is being executed or was finalized, per market id.
+    resolving_markets: UnorderedMap<u64, u64>,
+    /// History of staked governance tokens per account, oldest first.
+    stake_checkpoints: UnorderedMap<AccountId, Vec<StakeCheckpoint>>
 }
 
 impl Default for FluxDAO {
@@ -69,6 +194,28 @@ pub trait ResolutionResolver {
         &mut self,
         id: U64
     ) -> Promise;
+    fn on_multi_action(&mut self, id: U64, index: U64) -> PromiseOrValue<()>;
+    fn continue_actions(&mut self, id: U64, start: U64) -> PromiseOrValue<()>;
+}
+
+#[ext_contract]
+pub trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+#[ext_contract(ext_staking)]
+pub trait StakingResolver {
+    fn on_withdraw_unstaked(&mut self, account_id: AccountId, amount: U128);
+}
+
+#[ext_contract(ext_streams)]
+pub trait StreamResolver {
+    fn on_stream_withdraw(&mut self, id: U64, amount: U128);
+}
+
+#[ext_contract(ext_budgets)]
+pub trait BudgetResolver {
+    fn on_budget_spend(&mut self, spend_id: U64);
 }
 
 #[near_bindgen]
@@ -83,6 +230,7 @@ impl FluxDAO {
         protocol_address: String
     ) -> Self {
         assert!(!env::state_exists(), "The contract is already initialized");
+        assert!(grace_period.0 <= MAX_GRACE_PERIOD, "ERR_GRACE_PERIOD_TOO_LONG");
         let mut dao = Self {
             purpose,
             bond: bond.into(),
@@ -95,47 +243,410 @@ impl FluxDAO {
             council: UnorderedSet::new(b"c".to_vec()),
             proposals: Vector::new(b"p".to_vec()),
             last_voted: UnorderedMap::new(b"e".to_vec()),
-            protocol_address
+            protocol_address,
+            council_since: UnorderedMap::new(b"s".to_vec()),
+            inactivity_policy: InactivityPolicy {
+                window: U64(10),
+                min_participation: ParticipationRatio {
+                    numerator: U64(1),
+                    denominator: U64(2),
+                },
+            },
+            min_council: DEFAULT_MIN_COUNCIL,
+            max_council: DEFAULT_MAX_COUNCIL,
+            council_stake: 0,
+            stake_cooldown: 0,
+            invitations: UnorderedMap::new(b"i".to_vec()),
+            member_stakes: UnorderedMap::new(b"m".to_vec()),
+            pending_stakes: UnorderedMap::new(b"w".to_vec()),
+            voting_mode: VotingMode::Council,
+            staking_token: None,
+            unbonding_period: 0,
+            stakes: UnorderedMap::new(b"k".to_vec()),
+            total_staked: 0,
+            unbonding: UnorderedMap::new(b"u".to_vec()),
+            vote_locks: UnorderedMap::new(b"l".to_vec()),
+            delegations: UnorderedMap::new(b"d".to_vec()),
+            ft_balances: UnorderedMap::new(b"f".to_vec()),
+            locked_bonds: 0,
+            locked_stakes: 0,
+            streams: Vector::new(b"t".to_vec()),
+            locked_streams: 0,
+            budgets: UnorderedMap::new(b"b".to_vec()),
+            budget_spends: Vector::new(b"a".to_vec()),
+            timelocks: UnorderedMap::new(b"q".to_vec()),
+            guardians: UnorderedSet::new(b"g".to_vec()),
+            kind_grace_periods: UnorderedMap::new(b"r".to_vec()),
+            execution_window: DEFAULT_EXECUTION_WINDOW,
+            refund_expired_bonds: true,
+            reveal_periods: UnorderedMap::new(b"v".to_vec()),
+            vote_keys: UnorderedMap::new(b"n".to_vec()),
+            vote_nonces: UnorderedMap::new(b"o".to_vec()),
+            reporter_bond: DEFAULT_REPORTER_BOND,
+            reporter_reward: DEFAULT_REPORTER_REWARD,
+            dispute_window: 0,
+            dispute_bond: DEFAULT_DISPUTE_BOND,
+            resolutions: UnorderedMap::new(b"x".to_vec()),
+            resolving_markets: UnorderedMap::new(b"y".to_vec()),
+            stake_checkpoints: UnorderedMap::new(b"h".to_vec())
         };
+        // pausing the protocol is an emergency measure
+        dao.kind_grace_periods.insert(&"PauseProtocol".to_string(), &0);
+        dao.kind_grace_periods.insert(&"UnpauseProtocol".to_string(), &0);
         for account_id in council.clone() {
-            dao.council.insert(&account_id);
+            dao.add_council_member(&account_id);
         }
+        assert!(dao.council.len() >= dao.min_council, "ERR_COUNCIL_TOO_SMALL");
+        assert!(dao.council.len() <= dao.max_council, "ERR_COUNCIL_TOO_LARGE");
         dao
     }
 
+    /// Accounts outside the council can only propose market resolutions, with the reporter bond.
     #[payable]
     pub fn add_proposal(&mut self, proposal: ProposalInput) -> U64 {
         // TODO: add also extra storage cost for the proposal itself.
+        let (bond, reporter_reward) = if self.council.contains(&env::predecessor_account_id()) {
+            (self.bond, None)
+        } else {
+            match proposal.kind {
+                ProposalKind::ResoluteMarket { .. } => (self.reporter_bond, Some(self.reporter_reward)),
+                _ => env::panic(b"Only council can create proposals"),
+            }
+        };
         assert!(
-            proposal.description.len() < MAX_DESCRIPTION_LENGTH,
-            "Description length is too long"
+            env::attached_deposit() >= bond + metadata_storage_cost(&proposal),
+            "Not enough deposit"
+        );
+
+        self.insert_proposal(proposal, bond, reporter_reward)
+    }
+
+    /// Permissionless: creates a `RemoveCouncil` proposal for a council member whose participation
+    /// over the last `inactivity_policy.window` closed proposals is below the configured minimum.
+    #[payable]
+    pub fn propose_inactive_removal(&mut self, account_id: AccountId) -> U64 {
+        assert!(self.council.contains(&account_id), "ERR_NOT_IN_COUNCIL");
+        assert!(env::attached_deposit() >= self.bond, "Not enough deposit");
+        let window = self.inactivity_policy.window.0;
+        let (voted, total) = self.participation(&account_id, window);
+        assert!(total >= window, "Not enough closed proposals to judge participation");
+        assert!(self.inactivity_policy.is_inactive(voted, total), "Member is not inactive");
+
+        self.insert_proposal(ProposalInput {
+            description: format!("{} voted on {} of the last {} proposals", account_id, voted, total),
+            kind: ProposalKind::RemoveCouncil { target: account_id },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        }, self.bond, None)
+    }
+
+    /// Lets the proposer fix a proposal that is still being voted on. Votes are cleared and the
+    /// vote period restarts, the replaced version is kept in the proposal's amendment history.
+    /// Reporters can only amend into another market resolution, other proposers outside the
+    /// council can't amend.
+    #[payable]
+    pub fn amend_proposal(&mut self, id: U64, proposal: ProposalInput) {
+        let mut current = self.proposals.get(id.into()).expect("No proposal with such id");
+        assert_eq!(current.proposer, env::predecessor_account_id(), "Only the proposer can amend a proposal");
+        self.update_vote_status(&mut current);
+        assert_eq!(current.status, ProposalStatus::Vote, "ERR_VOTING_ENDED");
+        assert!(current.dispute.is_none(), "ERR_PROPOSAL_DISPUTED");
+        // a reporter's bond only covers market resolutions, like in `add_proposal`
+        if current.reporter_reward.is_some() {
+            match proposal.kind {
+                ProposalKind::ResoluteMarket { .. } => {}
+                _ => env::panic(b"Only council can create proposals"),
+            }
+        } else {
+            // e.g. an inactivity removal, its bond isn't the reporter bond
+            assert!(self.council.contains(&current.proposer), "Only council can amend proposals");
+        }
+        assert!(env::attached_deposit() >= metadata_storage_cost(&proposal), "Not enough deposit");
+        self.assert_valid_input(&proposal, id.into());
+        self.unlink_resolution(id.into(), &current.kind);
+        self.link_resolution(id.into(), &proposal.kind);
+
+        current.amendments.push(Amendment {
+            description: std::mem::replace(&mut current.description, proposal.description),
+            kind: std::mem::replace(&mut current.kind, proposal.kind),
+            depends_on: std::mem::replace(&mut current.depends_on, proposal.depends_on),
+            link: std::mem::replace(&mut current.link, proposal.link),
+            content_hash: std::mem::replace(&mut current.content_hash, proposal.content_hash.map(|hash| hash.to_lowercase())),
+            amended_at: env::block_timestamp().into(),
+        });
+        current.voting_mode = self.proposal_voting_mode(&current.kind);
+        current.total_weight = self.proposal_total_weight(&current.voting_mode).into();
+        current.snapshot_at = env::block_timestamp().into();
+        current.vote_period_end = env::block_timestamp() + self.vote_period;
+        current.reveal_period_end = self.reveal_period_end(&current.kind, current.vote_period_end);
+        current.commits = HashMap::default();
+        current.last_vote = 0;
+        current.decided_at = None;
+        current.vote_yes = 0;
+        current.vote_no = 0;
+        current.weight_yes = 0.into();
+        current.weight_no = 0.into();
+        current.votes = HashMap::default();
+        self.proposals.replace(id.into(), &current);
+        env::log(
+            format!(
+                "Proposal {} amended by {}, votes were reset (amendment {})",
+                id.0,
+                current.proposer,
+                current.amendments.len()
+            ).as_bytes()
         );
+    }
+
+    /// Checks the input of a proposal with the given id.
+    fn assert_valid_input(&self, proposal: &ProposalInput, id: u64) {
         assert!(
-            self.council.contains(&env::predecessor_account_id()),
-            "Only council can create proposals"
+            proposal.description.len() < MAX_DESCRIPTION_LENGTH,
+            "Description length is too long"
         );
-        assert!(env::attached_deposit() >= self.bond, "Not enough deposit");
+        if let Some(ref link) = proposal.link {
+            assert!(!link.is_empty() && link.len() <= MAX_LINK_LENGTH, "ERR_INVALID_LINK");
+        }
+        if let Some(ref content_hash) = proposal.content_hash {
+            assert!(
+                utils::is_sha256_hex(content_hash),
+                "ERR_INVALID_CONTENT_HASH"
+            );
+        }
+        self.assert_valid_kind(&proposal.kind);
+        assert!(proposal.depends_on.len() <= MAX_DEPENDENCIES, "ERR_TOO_MANY_DEPENDENCIES");
+        for (index, dependency) in proposal.depends_on.iter().enumerate() {
+            assert!(dependency.0 < id, "ERR_DEPENDENCY_NOT_FOUND");
+            assert!(!proposal.depends_on[..index].contains(dependency), "ERR_DUPLICATE_DEPENDENCY");
+        }
+        let payout: Balance = proposal.kind.actions().iter().map(|action| match action {
+            ProposalKind::Payout { amount, .. } => amount.0,
+            _ => 0,
+        }).sum();
+        assert!(payout <= self.spendable_balance(), "ERR_INSUFFICIENT_TREASURY_BALANCE");
+    }
+
+    fn reveal_period_end(&self, kind: &ProposalKind, vote_period_end: Duration) -> Option<Duration> {
+        self.reveal_periods.get(&kind.name().to_string()).map(|reveal_period| vote_period_end + reveal_period)
+    }
 
+    fn proposal_voting_mode(&self, kind: &ProposalKind) -> VotingMode {
+        match kind {
+            ProposalKind::ResoluteMarket { .. } => VotingMode::Council,
+            _ => self.voting_mode.clone(),
+        }
+    }
+
+    fn proposal_total_weight(&self, voting_mode: &VotingMode) -> Balance {
+        match voting_mode {
+            VotingMode::Council => 0,
+            VotingMode::TokenWeighted { .. } => self.total_staked,
+        }
+    }
+
+    fn insert_proposal(&mut self, proposal: ProposalInput, bond: Balance, reporter_reward: Option<Balance>) -> U64 {
+        self.locked_bonds += bond;
+        self.assert_valid_input(&proposal, self.proposals.len());
+        let voting_mode = self.proposal_voting_mode(&proposal.kind);
+        let vote_period_end = env::block_timestamp() + self.vote_period;
+        let reveal_period_end = self.reveal_period_end(&proposal.kind, vote_period_end);
         let p = Proposal {
             status: ProposalStatus::Vote,
             proposer: env::predecessor_account_id(),
+            bond: bond.into(),
+            reporter_reward: reporter_reward.map(|reward| reward.into()),
+            dispute: None,
             description: proposal.description,
             kind: proposal.kind,
             last_vote: 0,
-            vote_period_end: env::block_timestamp() + self.vote_period,
+            vote_period_end,
+            decided_at: None,
             vote_yes: 0,
             vote_no: 0,
             votes: HashMap::default(),
+            reveal_period_end,
+            commits: HashMap::default(),
+            total_weight: self.proposal_total_weight(&voting_mode).into(),
+            snapshot_at: env::block_timestamp().into(),
+            voting_mode,
+            weight_yes: 0.into(),
+            weight_no: 0.into(),
+            action_results: vec![],
+            depends_on: proposal.depends_on,
+            link: proposal.link,
+            content_hash: proposal.content_hash.map(|hash| hash.to_lowercase()),
+            amendments: vec![],
+            executing: false,
         };
 
+        self.link_resolution(self.proposals.len(), &p.kind);
         self.proposals.push(&p);
         U64(self.proposals.len() - 1)
     }
 
+    /// Adds a resolution proposal to the index of its market. Submitting the same outcome as an
+    /// active proposal is rejected, different outcomes are linked and have to be voted on.
+    fn link_resolution(&mut self, id: u64, kind: &ProposalKind) {
+        let (market_id, payout_numerator) = match *kind {
+            ProposalKind::ResoluteMarket { market_id, ref payout_numerator } => (market_id.0, payout_numerator),
+            _ => return,
+        };
+        if let Some(resolving) = self.resolving_markets.get(&market_id) {
+            let status = self.proposals.get(resolving).expect("ERR_PROPOSAL_NOT_FOUND").status;
+            assert!(status != ProposalStatus::Finalized, "ERR_MARKET_ALREADY_RESOLVED");
+        }
+        let mut ids = self.resolutions.get(&market_id).unwrap_or_default();
+        let mut conflicts = vec![];
+        for other_id in ids.iter() {
+            let other = self.proposals.get(*other_id).expect("ERR_PROPOSAL_NOT_FOUND");
+            if other.status.is_finished() {
+                continue;
+            }
+            if let ProposalKind::ResoluteMarket { payout_numerator: ref other_numerator, .. } = other.kind {
+                assert!(other_numerator != payout_numerator, "ERR_DUPLICATE_RESOLUTION");
+            }
+            conflicts.push(other_id.to_string());
+        }
+        if !conflicts.is_empty() {
+            env::log(
+                format!(
+                    "Resolution proposal {} for market {} conflicts with proposals {}",
+                    id,
+                    market_id,
+                    conflicts.join(", ")
+                ).as_bytes()
+            );
+        }
+        ids.push(id);
+        self.resolutions.insert(&market_id, &ids);
+    }
+
+    fn unlink_resolution(&mut self, id: u64, kind: &ProposalKind) {
+        if let ProposalKind::ResoluteMarket { market_id, .. } = *kind {
+            let mut ids = self.resolutions.get(&market_id.0).unwrap_or_default();
+            ids.retain(|other_id| *other_id != id);
+            self.resolutions.insert(&market_id.0, &ids);
+        }
+    }
+
+    /// Marks the proposal as the one resolving the market. Returns false if another resolution
+    /// was already finalized, panics while another one is being executed.
+    fn claim_market(&mut self, market_id: u64, id: u64) -> bool {
+        if let Some(resolving) = self.resolving_markets.get(&market_id) {
+            if resolving != id {
+                let status = self.proposals.get(resolving).expect("ERR_PROPOSAL_NOT_FOUND").status;
+                if status == ProposalStatus::Finalized {
+                    return false;
+                }
+                assert!(status.is_finished(), "ERR_RESOLUTION_IN_PROGRESS");
+            }
+        }
+        self.resolving_markets.insert(&market_id, &id);
+        true
+    }
+
+    /// Ids of all resolution proposals submitted for the market.
+    pub fn get_resolution_proposals(&self, market_id: U64) -> Vec<U64> {
+        self.resolutions
+            .get(&market_id.0)
+            .unwrap_or_default()
+            .into_iter()
+            .map(U64)
+            .collect()
+    }
+
+    fn assert_valid_kind(&self, kind: &ProposalKind) {
+        match kind {
+            ProposalKind::ChangeInactivityPolicy { policy } => {
+                assert!(
+                    policy.window.0 > 0 && policy.window.0 <= MAX_INACTIVITY_WINDOW,
+                    "ERR_INVALID_INACTIVITY_WINDOW"
+                );
+                let ratio = &policy.min_participation;
+                assert!(
+                    ratio.denominator.0 > 0 && ratio.numerator.0 <= ratio.denominator.0,
+                    "ERR_INVALID_PARTICIPATION_RATIO"
+                );
+            }
+            ProposalKind::ChangeCouncilBounds { min_council, max_council } => {
+                assert!(min_council.0 >= 1, "ERR_MIN_COUNCIL_ZERO");
+                assert!(min_council.0 <= max_council.0, "ERR_INVALID_COUNCIL_BOUNDS");
+            }
+            ProposalKind::ChangeGracePeriod { grace_period } => {
+                assert!(grace_period.0 <= MAX_GRACE_PERIOD, "ERR_GRACE_PERIOD_TOO_LONG");
+            }
+            ProposalKind::SetTimelock { kind: kind_name, .. } => {
+                assert!(ProposalKind::is_kind_name(kind_name), "ERR_UNKNOWN_PROPOSAL_KIND");
+            }
+            ProposalKind::SetKindGracePeriod { kind: kind_name, grace_period } => {
+                assert!(ProposalKind::is_kind_name(kind_name), "ERR_UNKNOWN_PROPOSAL_KIND");
+                if let Some(grace_period) = grace_period {
+                    assert!(grace_period.0 <= MAX_GRACE_PERIOD, "ERR_GRACE_PERIOD_TOO_LONG");
+                }
+            }
+            ProposalKind::ChangeExecutionWindow { execution_window, .. } => {
+                assert!(
+                    execution_window.0 == 0 || execution_window.0 >= MIN_EXECUTION_WINDOW,
+                    "ERR_EXECUTION_WINDOW_TOO_SHORT"
+                );
+            }
+            ProposalKind::ChangeReporterBond { bond, .. } => {
+                assert!(bond.0 > 0, "ERR_ZERO_REPORTER_BOND");
+            }
+            ProposalKind::ChangeDisputeSettings { bond, .. } => {
+                assert!(bond.0 > 0, "ERR_ZERO_DISPUTE_BOND");
+            }
+            ProposalKind::SetSecretVoting { kind: kind_name, reveal_period } => {
+                assert!(ProposalKind::is_kind_name(kind_name), "ERR_UNKNOWN_PROPOSAL_KIND");
+                if let Some(reveal_period) = reveal_period {
+                    assert!(reveal_period.0 > 0, "ERR_ZERO_REVEAL_PERIOD");
+                }
+            }
+            ProposalKind::SlashMember { amount, .. } => {
+                assert!(amount.0 > 0, "ERR_ZERO_SLASH");
+            }
+            ProposalKind::FtPayout { amount, .. } => {
+                assert!(amount.0 > 0, "ERR_ZERO_PAYOUT");
+            }
+            ProposalKind::CreateStream { total, start, end, cliff, .. } => {
+                assert!(total.0 > 0, "ERR_ZERO_PAYOUT");
+                assert!(start.0 < end.0, "ERR_INVALID_STREAM_PERIOD");
+                assert!(start.0 <= cliff.0 && cliff.0 <= end.0, "ERR_INVALID_STREAM_CLIFF");
+            }
+            ProposalKind::CancelStream { id } => {
+                assert!(id.0 < self.streams.len(), "ERR_STREAM_NOT_FOUND");
+            }
+            ProposalKind::GrantBudget { cap, period, .. } => {
+                assert!(cap.0 > 0, "ERR_ZERO_BUDGET");
+                assert!(period.0 > 0, "ERR_ZERO_BUDGET_PERIOD");
+            }
+            ProposalKind::MultiAction { actions } => {
+                assert!(!actions.is_empty() && actions.len() <= MAX_ACTIONS, "ERR_INVALID_ACTION_COUNT");
+                for action in actions {
+                    match action {
+                        ProposalKind::MultiAction { .. } | ProposalKind::ResoluteMarket { .. } => {
+                            env::panic(b"ERR_ACTION_NOT_ALLOWED")
+                        }
+                        _ => self.assert_valid_kind(action),
+                    }
+                }
+            }
+            _ => {}
+        }
+        if let Err(err) = self.check_executable(kind) {
+            env::panic(err.as_bytes());
+        }
+    }
+
     pub fn get_vote_period(&self) -> WrappedDuration {
         self.vote_period.into()
     }
 
+    pub fn get_grace_period(&self) -> WrappedDuration {
+        self.grace_period.into()
+    }
+
     pub fn get_bond(&self) -> WrappedBalance {
         self.bond.into()
     }
@@ -164,974 +675,4520 @@ impl FluxDAO {
         self.purpose.clone()
     }
 
-    fn update_vote_status(&self, proposal: &mut Proposal) {
-        proposal.status = match proposal.kind {
-            ProposalKind::ResoluteMarket{ ref market_id, ref payout_numerator } => {
-                proposal.vote_status(&RESOLUTE_POLICY, self.council.len())
-            }
-            _ => {
-                proposal.vote_status(&self.policy, self.council.len())
-            }
+    pub fn get_council_bounds(&self) -> CouncilBounds {
+        CouncilBounds {
+            min_council: self.min_council.into(),
+            max_council: self.max_council.into(),
         }
     }
 
-    pub fn vote(&mut self, id: U64, vote: Vote) {
-        assert!(
-            self.council.contains(&env::predecessor_account_id()),
-            "Only council can vote"
-        );
-        let mut proposal = self.proposals.get(id.into()).expect("No proposal with such id");
-        assert_eq!(
-            proposal.status,
-            ProposalStatus::Vote,
-            "Proposal not active voting"
-        );
-        assert!(proposal.vote_period_end > env::block_timestamp(), "voting period ended");
-        assert!(
-            !proposal.votes.contains_key(&env::predecessor_account_id()),
-            "Already voted"
-        );
-        match vote {
-            Vote::Yes => proposal.vote_yes += 1,
-            Vote::No => proposal.vote_no += 1,
+    pub fn get_council_stake(&self) -> WrappedBalance {
+        self.council_stake.into()
+    }
+
+    pub fn get_stake_cooldown(&self) -> WrappedDuration {
+        self.stake_cooldown.into()
+    }
+
+    /// Stake `account_id` has to deposit to accept their council seat, if invited.
+    pub fn get_invitation(&self, account_id: AccountId) -> Option<WrappedBalance> {
+        self.invitations.get(&account_id).map(|stake| stake.into())
+    }
+
+    pub fn get_member_stake(&self, account_id: AccountId) -> WrappedBalance {
+        self.member_stakes.get(&account_id).unwrap_or(0).into()
+    }
+
+    pub fn get_pending_stake(&self, account_id: AccountId) -> Option<PendingStake> {
+        self.pending_stakes.get(&account_id)
+    }
+
+    pub fn get_voting_mode(&self) -> VotingMode {
+        self.voting_mode.clone()
+    }
+
+    pub fn get_staking_token(&self) -> Option<AccountId> {
+        self.staking_token.clone()
+    }
+
+    pub fn get_unbonding_period(&self) -> WrappedDuration {
+        self.unbonding_period.into()
+    }
+
+    pub fn get_staked_balance(&self, account_id: AccountId) -> WrappedBalance {
+        self.stakes.get(&account_id).unwrap_or(0).into()
+    }
+
+    pub fn get_total_staked(&self) -> WrappedBalance {
+        self.total_staked.into()
+    }
+
+    pub fn get_unbonding(&self, account_id: AccountId) -> Option<PendingStake> {
+        self.unbonding.get(&account_id)
+    }
+
+    pub fn get_treasury_summary(&self) -> TreasurySummary {
+        TreasurySummary {
+            balance: env::account_balance().into(),
+            storage_locked: self.storage_locked_balance().into(),
+            bonds_locked: self.locked_bonds.into(),
+            stakes_locked: self.locked_stakes.into(),
+            streams_locked: self.locked_streams.into(),
+            spendable: self.spendable_balance().into(),
         }
-        proposal.votes.insert(env::predecessor_account_id(), vote);
-        self.last_voted.insert(&env::predecessor_account_id(), &id.into());
-        self.update_vote_status(&mut proposal);
-        proposal.last_vote = env::block_timestamp();
-        self.proposals.replace(id.into(), &proposal);
     }
 
-    fn proposal_success(&mut self, id: u64, proposal: &mut Proposal, bond: u128){
-        assert!(proposal.status == ProposalStatus::Success, "Wrong status on callback");
-        proposal.status = ProposalStatus::Finalized;
-        self.proposals.replace(id, &proposal);
+    fn storage_locked_balance(&self) -> Balance {
+        env::storage_usage() as Balance * STORAGE_PRICE_PER_BYTE
+    }
 
-        if bond > 0 {
-            Promise::new(proposal.proposer.clone()).transfer(bond);
+    /// NEAR balance not needed for storage, proposal bonds, membership stakes or streams.
+    fn spendable_balance(&self) -> Balance {
+        env::account_balance()
+            .saturating_sub(self.storage_locked_balance())
+            .saturating_sub(self.locked_bonds)
+            .saturating_sub(self.locked_stakes)
+            .saturating_sub(self.locked_streams)
+    }
+
+    pub fn get_num_streams(&self) -> U64 {
+        U64(self.streams.len())
+    }
+
+    pub fn get_stream(&self, id: U64) -> Stream {
+        self.streams.get(id.into()).expect("ERR_STREAM_NOT_FOUND")
+    }
+
+    pub fn get_stream_balance(&self, id: U64) -> StreamBalance {
+        let stream = self.get_stream(id);
+        let vested = stream.vested(env::block_timestamp());
+        StreamBalance {
+            vested: vested.into(),
+            withdrawn: stream.withdrawn,
+            withdrawable: (vested - stream.withdrawn.0).into(),
         }
     }
 
-    pub fn ft_resolve_protocol_call(
-        &mut self,
-        id: U64
-    ) {
+    /// Transfers everything vested so far to the recipient of the stream.
+    pub fn withdraw_stream(&mut self, id: U64) -> Promise {
+        let mut stream = self.get_stream(id);
+        assert_eq!(stream.recipient, env::predecessor_account_id(), "ERR_NOT_STREAM_RECIPIENT");
+        let amount = stream.vested(env::block_timestamp()) - stream.withdrawn.0;
+        assert!(amount > 0, "ERR_NOTHING_TO_WITHDRAW");
+        stream.withdrawn = (stream.withdrawn.0 + amount).into();
+        self.streams.replace(id.into(), &stream);
+
+        match stream.token {
+            Some(ref token_id) => {
+                fungible_token::ft_transfer(
+                    stream.recipient.clone(),
+                    amount.into(),
+                    None,
+                    token_id,
+                    ONE_YOCTO,
+                    FT_TRANSFER_GAS,
+                ).then(ext_streams::on_stream_withdraw(
+                    id,
+                    amount.into(),
+                    &env::current_account_id(),
+                    0,
+                    FT_TRANSFER_GAS,
+                ))
+            }
+            None => {
+                self.locked_streams -= amount;
+                Promise::new(stream.recipient).transfer(amount)
+            }
+        }
+    }
+
+    pub fn on_stream_withdraw(&mut self, id: U64, amount: U128) {
         utils::assert_self();
-        let mut proposal = self.proposals.get(id.into()).expect("No proposal with such id");
         match env::promise_result(0) {
             PromiseResult::NotReady => unreachable!(),
-            PromiseResult::Successful(value) => {
-                self.proposal_success(id.into(), &mut proposal, self.bond)
-            }
-            PromiseResult::Failed => {},
+            PromiseResult::Successful(_) => {},
+            PromiseResult::Failed => {
+                let mut stream = self.get_stream(id);
+                stream.withdrawn = (stream.withdrawn.0 - amount.0).into();
+                self.streams.replace(id.into(), &stream);
+            },
         };
     }
 
-    pub fn finalize_external(&mut self, id: U64) -> Promise {
+    /// Grace period that applies to the given proposal kind.
+    pub fn get_kind_grace_period(&self, kind: String) -> WrappedDuration {
+        self.kind_grace_period(&kind).into()
+    }
+
+    fn kind_grace_period(&self, kind: &str) -> Duration {
+        self.kind_grace_periods.get(&kind.to_string()).unwrap_or(self.grace_period)
+    }
+
+    pub fn get_execution_window(&self) -> WrappedDuration {
+        self.execution_window.into()
+    }
+
+    pub fn get_refund_expired_bonds(&self) -> bool {
+        self.refund_expired_bonds
+    }
+
+    /// Disputes a passed market resolution during the dispute window. The resolution goes back to
+    /// a council vote that needs `DISPUTE_POLICY` to uphold it, the losing side's bond goes to the winner.
+    #[payable]
+    pub fn dispute(&mut self, id: U64) {
         let mut proposal = self.proposals.get(id.into()).expect("No proposal with such id");
-        assert!(
-            !proposal.status.is_finished(),
-            "Proposal already finalized"
+        self.update_vote_status(&mut proposal);
+        assert!(self.can_be_disputed(&proposal), "ERR_NOT_DISPUTABLE");
+        assert_eq!(proposal.status, ProposalStatus::Success, "ERR_NOT_DISPUTABLE");
+        let decided_at = proposal.decided_at.expect("ERR_OUTCOME_NOT_DECIDED");
+        assert!(env::block_timestamp() < decided_at + self.dispute_window, "ERR_DISPUTE_WINDOW_ENDED");
+        assert!(env::attached_deposit() >= self.dispute_bond, "Not enough deposit");
+
+        self.locked_bonds += self.dispute_bond;
+        proposal.dispute = Some(Dispute {
+            disputer: env::predecessor_account_id(),
+            bond: self.dispute_bond.into(),
+            disputed_at: env::block_timestamp().into(),
+        });
+        proposal.status = ProposalStatus::Vote;
+        proposal.vote_period_end = env::block_timestamp() + self.vote_period;
+        proposal.reveal_period_end = self.reveal_period_end(&proposal.kind, proposal.vote_period_end);
+        proposal.commits = HashMap::default();
+        proposal.decided_at = None;
+        proposal.vote_yes = 0;
+        proposal.vote_no = 0;
+        proposal.votes = HashMap::default();
+        self.proposals.replace(id.into(), &proposal);
+        env::log(
+            format!(
+                "Resolution proposal {} disputed by {}, it needs a new vote",
+                id.0,
+                env::predecessor_account_id()
+            ).as_bytes()
         );
+    }
+
+    /// Whether the proposal is a market resolution that hasn't been disputed yet.
+    fn can_be_disputed(&self, proposal: &Proposal) -> bool {
         match proposal.kind {
-            ProposalKind::PauseProtocol{ } => {
-                // no grace period
-            }
-            ProposalKind::UnpauseProtocol{ } => {
-                // no grace period
-            }
-            _ => {
-                assert!(env::block_timestamp() > proposal.last_vote + self.grace_period, "Grace period active");
-            }
+            ProposalKind::ResoluteMarket { .. } => self.dispute_window > 0 && proposal.dispute.is_none(),
+            _ => false,
         }
-        self.update_vote_status(&mut proposal);
-        self.proposals.replace(id.into(), &proposal);
-        let prom: Promise = match proposal.status {
-            ProposalStatus::Success => {
-                match proposal.kind {
-                    ProposalKind::ResoluteMarket{ ref market_id, ref payout_numerator } => {
-                        // base gas + gas for each enumerator
-                        let resolute_gas = match payout_numerator {
-                            Some(payout_vec) => payout_vec.len() as u64 * RESOLUTION_GAS,
-                            None => RESOLUTION_GAS
-                        };
-                        flux_protocol::resolute_market(
-                            *market_id,
-                            payout_numerator.clone(),
-                            &self.protocol_address,
-                            0,
-                            resolute_gas,
-                        )
-                    },
-                    ProposalKind::SetTokenWhitelist{ ref whitelist } => {
-                        flux_protocol::set_token_whitelist(
-                            whitelist.clone(),
-                            &self.protocol_address,
-                            0,
-                            RESOLUTION_GAS,
-                        )
-                    },
-                    ProposalKind::AddTokenWhitelist{ ref to_add } => {
-                        flux_protocol::add_to_token_whitelist(
-                            to_add.clone(),
-                            &self.protocol_address,
-                            0,
-                            RESOLUTION_GAS,
-                        )
-                    },
-                    ProposalKind::SetGov{ ref new_gov } => {
-                        flux_protocol::set_gov(
-                            new_gov.clone(),
-                            &self.protocol_address,
-                            0,
-                            RESOLUTION_GAS,
-                        )
-                    },
-                    ProposalKind::PauseProtocol{ } => {
-                        flux_protocol::pause(
-                            &self.protocol_address,
-                            0,
-                            RESOLUTION_GAS,
-                        )
-                    },
-                    ProposalKind::UnpauseProtocol{ } => {
-                        flux_protocol::unpause(
-                            &self.protocol_address,
-                            0,
-                            RESOLUTION_GAS,
-                        )
-                    },
-                    _ => {
-                        env::panic(b"not an external proposal")
-                    }
-                }
-            }
-            ProposalStatus::Reject => {
-                proposal.status = ProposalStatus::Rejected;
-                self.proposals.replace(id.into(), &proposal);
-                Promise::new(proposal.proposer.clone()).transfer(self.bond)
-            }
-            _ => {
-                env::panic(b"voting period has not expired and no majority vote yet")
-            }
-        };
+    }
 
-        if proposal.status == ProposalStatus::Success {
-            prom.then(ext_self::ft_resolve_protocol_call(
-                id,
-                &env::current_account_id(),
-                0,
-                RESOLUTION_GAS,
-            ))
-        } else {
-            prom
+    /// Unlocks the dispute bond of the proposal, if any, and pays it to `receiver`.
+    fn pay_dispute_bond(&mut self, proposal: &Proposal, receiver: Option<&AccountId>) {
+        if let Some(ref dispute) = proposal.dispute {
+            self.locked_bonds -= dispute.bond.0;
+            let receiver = receiver.unwrap_or(&dispute.disputer);
+            if dispute.bond.0 > 0 {
+                Promise::new(receiver.clone()).transfer(dispute.bond.0);
+            }
         }
     }
 
-    pub fn finalize(&mut self, id: U64) {
+    pub fn get_dispute_window(&self) -> WrappedDuration {
+        self.dispute_window.into()
+    }
+
+    pub fn get_dispute_bond(&self) -> WrappedBalance {
+        self.dispute_bond.into()
+    }
+
+    pub fn get_reporter_bond(&self) -> WrappedBalance {
+        self.reporter_bond.into()
+    }
+
+    pub fn get_reporter_reward(&self) -> WrappedBalance {
+        self.reporter_reward.into()
+    }
+
+    /// Reveal window of the given proposal kind, `None` if its votes are public.
+    pub fn get_reveal_period(&self, kind: String) -> Option<WrappedDuration> {
+        self.reveal_periods.get(&kind).map(|reveal_period| reveal_period.into())
+    }
+
+    pub fn get_timelock(&self, kind: String) -> WrappedDuration {
+        self.timelocks.get(&kind).unwrap_or(0).into()
+    }
+
+    pub fn get_guardians(&self) -> Vec<AccountId> {
+        self.guardians.to_vec()
+    }
+
+    /// Lets a guardian cancel a queued proposal before its timelock ends, the bond is returned.
+    pub fn veto_queued(&mut self, id: U64) {
+        assert!(self.guardians.contains(&env::predecessor_account_id()), "Only guardians can veto");
         let mut proposal = self.proposals.get(id.into()).expect("No proposal with such id");
-        assert!(
-            !proposal.status.is_finished(),
-            "Proposal already finalized"
-        );
-        match proposal.kind {
-            ProposalKind::PauseProtocol{ } => {
-                // no grace period
-            }
-            ProposalKind::UnpauseProtocol{ } => {
-                // no grace period
-            }
-            _ => {
-                assert!(env::block_timestamp() > proposal.last_vote + self.grace_period, "Grace period active");
+        match proposal.status {
+            ProposalStatus::Queued { eta } => {
+                assert!(env::block_timestamp() < eta.0, "ERR_TIMELOCK_ENDED");
             }
+            _ => env::panic(b"ERR_PROPOSAL_NOT_QUEUED"),
         }
-        self.update_vote_status(&mut proposal);
-        let actual_bond = self.bond;
-        match proposal.status {
-            ProposalStatus::Success => {
-                // env::log(b"Vote succeeded");
-                match proposal.kind {
-                    ProposalKind::NewCouncil { ref target } => {
-                        self.council.insert(&target.clone());
-                    }
-                    ProposalKind::RemoveCouncil { ref target } => {
-                        self.kick_user(&target.clone());
-                    }
-                    ProposalKind::Payout { ref target, amount } => {
-                        Promise::new(target.clone()).transfer(amount.0);
-                    }
-                    ProposalKind::ChangeVotePeriod { vote_period } => {
-                        self.vote_period = vote_period.into();
-                    }
-                    ProposalKind::ChangeBond { bond } => {
-                        self.bond = bond.into();
-                    }
-                    ProposalKind::ChangePolicy{ ref policy } => {
-                        self.policy = policy.clone();
-                    }
-                    ProposalKind::ChangePurpose{ ref purpose } => {
-                        self.purpose = purpose.clone();
-                    },
-                    ProposalKind::ChangeProtocolAddress{ ref address } => {
-                        self.protocol_address = address.to_string();
-                    },
-                    _ => {
-                        env::panic(b"not an internal proposal")
-                    }
-                }
+        proposal.status = ProposalStatus::Vetoed;
+        self.proposals.replace(id.into(), &proposal);
+        let bond = self.release_bond(&proposal);
+        if bond > 0 {
+            Promise::new(proposal.proposer.clone()).transfer(bond);
+        }
+        self.pay_dispute_bond(&proposal, None);
+    }
+
+    /// Updates the vote status and makes sure the grace period of a successful proposal has
+    /// passed, moving it into the timelock queue if its kind has a delay.
+    /// Proposals not executed within the execution window expire.
+    /// Returns whether the proposal can be executed or rejected now.
+    fn resolve_status(&mut self, id: u64, proposal: &mut Proposal) -> bool {
+        if let ProposalStatus::Queued { eta } = proposal.status {
+            assert!(env::block_timestamp() >= eta.0, "ERR_TIMELOCK_ACTIVE");
+            return !self.expire_if_stale(id, proposal, eta.0);
+        }
+        self.update_vote_status(proposal);
+        if proposal.status == ProposalStatus::Success {
+            let decided_at = proposal.decided_at.expect("ERR_OUTCOME_NOT_DECIDED");
+            let (mut grace_period, delay) = {
+                let actions = proposal.kind.actions();
+                let grace_period = actions.iter().map(|action| self.kind_grace_period(action.name())).max().unwrap_or(0);
+                let delay = actions
+                    .iter()
+                    .map(|action| self.timelocks.get(&action.name().to_string()).unwrap_or(0))
+                    .max()
+                    .unwrap_or(0);
+                (grace_period, delay)
+            };
+            if self.can_be_disputed(proposal) {
+                grace_period = std::cmp::max(grace_period, self.dispute_window);
             }
-            ProposalStatus::Reject => {
-                proposal.status = ProposalStatus::Rejected;
-                Promise::new(proposal.proposer.clone()).transfer(self.bond);
+            assert!(
+                env::block_timestamp() >= decided_at + grace_period,
+                "Grace period active"
+            );
+            if self.expire_if_stale(id, proposal, decided_at + grace_period) {
+                return false;
             }
-            _ => {
-                env::panic(b"voting period has not expired and no majority vote yet")
+            if delay > 0 {
+                proposal.status = ProposalStatus::Queued { eta: (env::block_timestamp() + delay).into() };
+                self.proposals.replace(id, proposal);
+                return false;
             }
-        };
+        }
+        true
+    }
 
-        self.proposals.replace(id.into(), &proposal);
-        if proposal.status == ProposalStatus::Success{
-            self.proposal_success(id.into(), &mut proposal, actual_bond);
+    /// Marks the proposal `Expired` if its execution window, starting at `executable_at`, has ended.
+    fn expire_if_stale(&mut self, id: u64, proposal: &mut Proposal, executable_at: Timestamp) -> bool {
+        if self.execution_window == 0 || env::block_timestamp() < executable_at + self.execution_window {
+            return false;
+        }
+        proposal.status = ProposalStatus::Expired;
+        self.proposals.replace(id, proposal);
+        let bond = self.release_bond(proposal);
+        if self.refund_expired_bonds && bond > 0 {
+            Promise::new(proposal.proposer.clone()).transfer(bond);
         }
+        self.pay_dispute_bond(proposal, None);
+        true
     }
 
-    pub fn exit_dao(&mut self) {
-        self.kick_user(&env::predecessor_account_id());
+    pub fn get_budget(&self, account_id: AccountId) -> Option<Budget> {
+        self.budgets.get(&account_id)
     }
 
-    fn kick_user(&mut self, account_id: &AccountId) {
-        let proposalid = self.last_voted.get(account_id);
-        if !proposalid.is_none() {
-            let proposal = self.proposals.get(proposalid.unwrap()).expect("ERR_PROPOSAL_NOT_FOUND");
+    pub fn get_num_budget_spends(&self) -> U64 {
+        U64(self.budget_spends.len())
+    }
 
-            match proposal.kind {
-                ProposalKind::RemoveCouncil { target } => {
-                    if &target != account_id {
-                        assert!(proposal.status != ProposalStatus::Vote, "ERR_VOTING_ACTIVE");
+    pub fn get_budget_spends(&self, from_index: U64, limit: U64) -> Vec<BudgetSpend> {
+        let from_index_u:u64 = from_index.into();
+        let limit_u:u64 = limit.into();
+        (from_index_u..std::cmp::min(from_index_u + limit_u, self.budget_spends.len()))
+            .map(|index| self.budget_spends.get(index).unwrap())
+            .collect()
+    }
+
+    /// Pays `amount` from the caller's budget without a vote, as long as the cap for the
+    /// current period isn't exceeded.
+    pub fn spend_from_budget(&mut self, target: AccountId, amount: U128, memo: String) -> Promise {
+        assert!(memo.len() < MAX_MEMO_LENGTH, "Memo length is too long");
+        let spender = env::predecessor_account_id();
+        let mut budget = self.budgets.get(&spender).expect("ERR_NO_BUDGET");
+        budget.roll_period(env::block_timestamp());
+        assert!(amount.0 > 0 && amount.0 <= budget.remaining(), "ERR_BUDGET_EXCEEDED");
+        budget.spent_in_period = (budget.spent_in_period.0 + amount.0).into();
+        self.budgets.insert(&spender, &budget);
+
+        let spend = BudgetSpend {
+            spender: spender.clone(),
+            target: target.clone(),
+            token: budget.token.clone(),
+            amount,
+            memo,
+            timestamp: env::block_timestamp().into(),
+            period_start: budget.period_start,
+            failed: false,
+        };
+        env::log(
+            format!(
+                "Budget spend by {} to {} of {} {}: {}",
+                spender,
+                target,
+                amount.0,
+                budget.token.clone().unwrap_or_else(|| "NEAR".to_string()),
+                spend.memo
+            ).as_bytes()
+        );
+        self.budget_spends.push(&spend);
+        let spend_id = U64(self.budget_spends.len() - 1);
+
+        match budget.token {
+            Some(ref token_id) => {
+                let balance = self.ft_balances.get(token_id).unwrap_or(0);
+                assert!(balance >= amount.0, "ERR_INSUFFICIENT_TREASURY_BALANCE");
+                self.ft_balances.insert(token_id, &(balance - amount.0));
+                fungible_token::ft_transfer(
+                    target,
+                    amount,
+                    None,
+                    token_id,
+                    ONE_YOCTO,
+                    FT_TRANSFER_GAS,
+                ).then(ext_budgets::on_budget_spend(
+                    spend_id,
+                    &env::current_account_id(),
+                    0,
+                    FT_TRANSFER_GAS,
+                ))
+            }
+            None => {
+                assert!(amount.0 <= self.spendable_balance(), "ERR_INSUFFICIENT_TREASURY_BALANCE");
+                Promise::new(target).transfer(amount.0)
+            }
+        }
+    }
+
+    pub fn on_budget_spend(&mut self, spend_id: U64) {
+        utils::assert_self();
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(_) => {},
+            PromiseResult::Failed => {
+                let mut spend = self.budget_spends.get(spend_id.into()).expect("ERR_SPEND_NOT_FOUND");
+                spend.failed = true;
+                self.budget_spends.replace(spend_id.into(), &spend);
+
+                let token_id = spend.token.clone().expect("ERR_NOT_TOKEN_SPEND");
+                let balance = self.ft_balances.get(&token_id).unwrap_or(0);
+                self.ft_balances.insert(&token_id, &(balance + spend.amount.0));
+                // only give the allowance back if it's still the budget and period that was charged
+                if let Some(mut budget) = self.budgets.get(&spend.spender) {
+                    if budget.token == spend.token && budget.period_start.0 == spend.period_start.0 {
+                        budget.spent_in_period = budget.spent_in_period.0.saturating_sub(spend.amount.0).into();
+                        self.budgets.insert(&spend.spender, &budget);
                     }
-                },
-                _ => {
-                    assert!(proposal.status != ProposalStatus::Vote, "ERR_VOTING_ACTIVE");
                 }
+            },
+        };
+    }
+
+    fn create_stream(&mut self, stream: Stream) {
+        match stream.token {
+            Some(ref token_id) => {
+                let balance = self.ft_balances.get(token_id).unwrap_or(0);
+                assert!(balance >= stream.total.0, "ERR_INSUFFICIENT_TREASURY_BALANCE");
+                self.ft_balances.insert(token_id, &(balance - stream.total.0));
+            }
+            None => {
+                assert!(stream.total.0 <= self.spendable_balance(), "ERR_INSUFFICIENT_TREASURY_BALANCE");
+                self.locked_streams += stream.total.0;
             }
         }
-        assert!(self.council.remove(account_id), "ERR_NOT_IN_COUNCIL");
+        self.streams.push(&stream);
     }
-}
 
-#[cfg(not(target_arch = "wasm32"))]
-#[cfg(test)]
-mod tests {
-    use near_sdk::MockedBlockchain;
-    use near_sdk::{testing_env, VMContext};
+    /// Stops vesting, the unvested remainder goes back to the treasury.
+    fn cancel_stream(&mut self, id: U64) {
+        let mut stream = self.get_stream(id);
+        assert!(stream.cancelled_at.is_none(), "ERR_STREAM_CANCELLED");
+        let now = env::block_timestamp();
+        let unvested = stream.total.0 - stream.vested(now);
+        stream.cancelled_at = Some(now.into());
+        self.streams.replace(id.into(), &stream);
 
-    use super::*;
+        match stream.token {
+            Some(ref token_id) => {
+                let balance = self.ft_balances.get(token_id).unwrap_or(0);
+                self.ft_balances.insert(token_id, &(balance + unvested));
+            }
+            None => {
+                self.locked_streams -= unvested;
+            }
+        }
+    }
+
+    /// Fungible token balances held by the treasury.
+    pub fn get_ft_balances(&self) -> Vec<(AccountId, WrappedBalance)> {
+        self.ft_balances
+            .iter()
+            .map(|(token_id, balance)| (token_id, balance.into()))
+            .collect()
+    }
+
+    pub fn get_ft_balance(&self, token_id: AccountId) -> WrappedBalance {
+        self.ft_balances.get(&token_id).unwrap_or(0).into()
+    }
+
+    pub fn get_inactivity_policy(&self) -> InactivityPolicy {
+        self.inactivity_policy.clone()
+    }
+
+    /// Participation of `account_id` over the last `last_n` closed proposals since they joined the council.
+    pub fn get_participation(&self, account_id: AccountId, last_n: U64) -> Participation {
+        let (voted, total) = self.participation(&account_id, std::cmp::min(last_n.0, MAX_INACTIVITY_WINDOW));
+        Participation {
+            voted: U64(voted),
+            total: U64(total),
+        }
+    }
+
+    /// Counts (voted, total) over the last `window` proposals that are no longer open for voting,
+    /// ignoring proposals created before the member joined the council.
+    fn participation(&self, account_id: &AccountId, window: u64) -> (u64, u64) {
+        let since = self.council_since.get(account_id).unwrap_or(0);
+        let mut voted = 0;
+        let mut total = 0;
+        let mut index = self.proposals.len();
+        let mut scanned = 0;
+        while index > since && total < window && scanned < MAX_PARTICIPATION_SCAN {
+            index -= 1;
+            scanned += 1;
+            let proposal = self.proposals.get(index).unwrap();
+            if proposal.voting_mode != VotingMode::Council {
+                continue;
+            }
+            if proposal.status == ProposalStatus::Vote && proposal.vote_period_end > env::block_timestamp() {
+                continue;
+            }
+            total += 1;
+            if proposal.votes.contains_key(account_id) {
+                voted += 1;
+            }
+        }
+        (voted, total)
+    }
+
+    fn update_vote_status(&self, proposal: &mut Proposal) {
+        proposal.status = self.vote_status(proposal);
+        if proposal.decided_at.is_none() {
+            proposal.decided_at = match proposal.status {
+                // not `now` alone, a success first seen on execution would never be past its grace period
+                ProposalStatus::Success => Some(std::cmp::min(
+                    env::block_timestamp(),
+                    proposal.reveal_period_end.unwrap_or(proposal.vote_period_end),
+                )),
+                // a rejection is decided by the vote period running out
+                ProposalStatus::Reject => Some(proposal.reveal_period_end.unwrap_or(proposal.vote_period_end)),
+                _ => None,
+            };
+        }
+    }
+
+    /// Outcome of the vote on the proposal so far.
+    fn vote_status(&self, proposal: &Proposal) -> ProposalStatus {
+        if let Some(reveal_period_end) = proposal.reveal_period_end {
+            // the tally is only known once the reveal window ended
+            if env::block_timestamp() < reveal_period_end {
+                return ProposalStatus::Vote;
+            }
+        }
+        match proposal.kind {
+            ProposalKind::ResoluteMarket{ ref market_id, ref payout_numerator } => match proposal.dispute {
+                Some(_) => {
+                    let num_council = self.council.len();
+                    let votes = std::cmp::max(
+                        DISPUTE_POLICY.num_votes(num_council),
+                        RESOLUTE_POLICY.num_votes(num_council),
+                    );
+                    let policy = PolicyItem { max_amount: U128(0), votes: NumOrRatio::Number(votes) };
+                    proposal.vote_status(&policy, num_council)
+                }
+                None => proposal.vote_status(&RESOLUTE_POLICY, self.council.len()),
+            }
+            _ => match proposal.voting_mode {
+                VotingMode::Council => proposal.vote_status(&self.policy, self.council.len()),
+                VotingMode::TokenWeighted { .. } => proposal.weighted_vote_status(&self.policy),
+            }
+        }
+    }
+
+    /// Votes on a proposal, also casting the vote for council members that delegated to the
+    /// caller for this kind of proposal and haven't voted themselves.
+    pub fn vote(&mut self, id: U64, vote: Vote) {
+        self.cast_vote(id, env::predecessor_account_id(), vote);
+    }
+
+    /// Casts a council vote signed off-chain by `account` with one of its registered vote keys.
+    /// The signature is over `"<dao account>:<id>:<Yes|No>:<account>:<nonce>"`, `nonce` has to be
+    /// higher than the last one used by the account.
+    pub fn vote_with_signature(
+        &mut self,
+        id: U64,
+        vote: Vote,
+        account: AccountId,
+        public_key: Base58PublicKey,
+        signature: Base64VecU8,
+        nonce: U64
+    ) {
+        let keys = self.vote_keys.get(&account).unwrap_or_default();
+        assert!(keys.contains(&public_key), "ERR_UNKNOWN_VOTE_KEY");
+        assert!(nonce.0 > self.vote_nonces.get(&account).unwrap_or(0), "ERR_NONCE_USED");
+        let message = format!(
+            "{}:{}:{}:{}:{}",
+            env::current_account_id(),
+            id.0,
+            match vote {
+                Vote::Yes => "Yes",
+                Vote::No => "No",
+            },
+            account,
+            nonce.0
+        );
+        assert!(
+            utils::verify_ed25519(&public_key, message.as_bytes(), &signature.0),
+            "ERR_INVALID_SIGNATURE"
+        );
+        self.vote_nonces.insert(&account, &nonce.0);
+        self.cast_vote(id, account, vote);
+    }
+
+    /// Registers an ed25519 key the caller can sign relayed votes with.
+    pub fn add_vote_key(&mut self, public_key: Base58PublicKey) {
+        let account_id = env::predecessor_account_id();
+        assert!(self.council.contains(&account_id), "ERR_NOT_IN_COUNCIL");
+        assert!(utils::is_ed25519_key(&public_key), "ERR_NOT_ED25519_KEY");
+        let mut keys = self.vote_keys.get(&account_id).unwrap_or_default();
+        assert!(!keys.contains(&public_key), "ERR_VOTE_KEY_EXISTS");
+        assert!(keys.len() < MAX_VOTE_KEYS, "ERR_TOO_MANY_VOTE_KEYS");
+        keys.push(public_key);
+        self.vote_keys.insert(&account_id, &keys);
+    }
+
+    pub fn remove_vote_key(&mut self, public_key: Base58PublicKey) {
+        let account_id = env::predecessor_account_id();
+        let mut keys = self.vote_keys.get(&account_id).unwrap_or_default();
+        keys.retain(|key| *key != public_key);
+        if keys.is_empty() {
+            self.vote_keys.remove(&account_id);
+        } else {
+            self.vote_keys.insert(&account_id, &keys);
+        }
+    }
+
+    pub fn get_vote_keys(&self, account_id: AccountId) -> Vec<Base58PublicKey> {
+        self.vote_keys.get(&account_id).unwrap_or_default()
+    }
+
+    pub fn get_vote_nonce(&self, account_id: AccountId) -> U64 {
+        U64(self.vote_nonces.get(&account_id).unwrap_or(0))
+    }
+
+    /// Votes on several proposals with the same checks as `vote`. A vote that can't be cast
+    /// doesn't abort the batch, its error is returned instead.
+    pub fn vote_batch(&mut self, votes: Vec<(U64, Vote)>) -> Vec<VoteResult> {
+        let voter = env::predecessor_account_id();
+        votes
+            .into_iter()
+            .map(|(id, vote)| VoteResult {
+                id,
+                error: self.try_cast_vote(id, voter.clone(), vote).err().map(String::from),
+            })
+            .collect()
+    }
+
+    fn cast_vote(&mut self, id: U64, voter: AccountId, vote: Vote) {
+        if let Err(err) = self.try_cast_vote(id, voter, vote) {
+            env::panic(err.as_bytes());
+        }
+    }
+
+    fn try_cast_vote(&mut self, id: U64, voter: AccountId, vote: Vote) -> Result<(), &'static str> {
+        if !self.council.contains(&voter) {
+            return Err("Only council can vote");
+        }
+        let mut proposal = self.proposals.get(id.into()).ok_or("No proposal with such id")?;
+        if proposal.voting_mode != VotingMode::Council {
+            return Err("Proposal is token weighted");
+        }
+        if proposal.reveal_period_end.is_some() {
+            return Err("ERR_SECRET_VOTING");
+        }
+        self.check_can_vote(&proposal, &voter)?;
+
+        let mut voters = self.delegators_of(&voter, proposal.kind.name());
+        voters.retain(|account_id| !proposal.votes.contains_key(account_id));
+        voters.push(voter);
+        for account_id in voters {
+            match vote {
+                Vote::Yes => proposal.vote_yes += 1,
+                Vote::No => proposal.vote_no += 1,
+            }
+            proposal.votes.insert(account_id.clone(), vote);
+            self.record_last_voted(&account_id, id.into(), proposal.vote_period_end);
+        }
+        self.update_vote_status(&mut proposal);
+        proposal.last_vote = env::block_timestamp();
+        self.proposals.replace(id.into(), &proposal);
+        Ok(())
+    }
+
+    /// Remembers the proposal of an account's vote that stays open the longest, so leaving the
+    /// council is blocked until it's closed regardless of the order votes were cast in.
+    fn record_last_voted(&mut self, account_id: &AccountId, id: u64, vote_period_end: Duration) {
+        let keep_previous = self.last_voted
+            .get(account_id)
+            .and_then(|previous| self.proposals.get(previous))
+            .map_or(false, |previous| {
+                previous.status == ProposalStatus::Vote && previous.vote_period_end > vote_period_end
+            });
+        if !keep_previous {
+            self.last_voted.insert(account_id, &id);
+        }
+    }
+
+    /// Commits to a vote on a proposal using commit-reveal voting, `hash` is the hex encoded
+    /// sha256 of `"<id>:<account>:<Yes|No>:<salt>"`. The vote has to be revealed after the vote period.
+    pub fn commit_vote(&mut self, id: U64, hash: String) {
+        let account_id = env::predecessor_account_id();
+        let mut proposal = self.proposals.get(id.into()).expect("No proposal with such id");
+        let reveal_period_end = proposal.reveal_period_end.expect("ERR_NOT_SECRET_VOTING");
+        self.assert_can_vote(&proposal, &account_id);
+        assert!(!proposal.commits.contains_key(&account_id), "Already voted");
+        assert!(utils::is_sha256_hex(&hash), "ERR_INVALID_COMMITMENT");
+        match proposal.voting_mode {
+            VotingMode::Council => {
+                assert!(self.council.contains(&account_id), "Only council can vote");
+            }
+            VotingMode::TokenWeighted { .. } => {
+                assert!(self.voting_weight(&account_id, &proposal) > 0, "ERR_NO_VOTING_POWER");
+                // the weight is taken when revealing, keep the stake until then
+                let locked_until = std::cmp::max(self.vote_locks.get(&account_id).unwrap_or(0), reveal_period_end);
+                self.vote_locks.insert(&account_id, &locked_until);
+            }
+        }
+        proposal.commits.insert(account_id.clone(), hash.to_lowercase());
+        self.record_last_voted(&account_id, id.into(), reveal_period_end);
+        proposal.last_vote = env::block_timestamp();
+        self.proposals.replace(id.into(), &proposal);
+    }
+
+    /// Reveals a committed vote during the reveal window, unrevealed commitments count as abstaining.
+    pub fn reveal_vote(&mut self, id: U64, vote: Vote, salt: String) {
+        let account_id = env::predecessor_account_id();
+        let mut proposal = self.proposals.get(id.into()).expect("No proposal with such id");
+        let reveal_period_end = proposal.reveal_period_end.expect("ERR_NOT_SECRET_VOTING");
+        assert!(
+            env::block_timestamp() >= proposal.vote_period_end && env::block_timestamp() < reveal_period_end,
+            "ERR_NOT_REVEAL_PERIOD"
+        );
+        let commitment = proposal.commits.get(&account_id).expect("ERR_NO_COMMITMENT");
+        assert_eq!(*commitment, utils::vote_commitment(id.0, &account_id, vote, &salt), "ERR_COMMITMENT_MISMATCH");
+        assert!(!proposal.votes.contains_key(&account_id), "Already voted");
+        match proposal.voting_mode {
+            VotingMode::Council => match vote {
+                Vote::Yes => proposal.vote_yes += 1,
+                Vote::No => proposal.vote_no += 1,
+            },
+            VotingMode::TokenWeighted { .. } => {
+                let weight = self.voting_weight(&account_id, &proposal);
+                match vote {
+                    Vote::Yes => proposal.weight_yes = (proposal.weight_yes.0 + weight).into(),
+                    Vote::No => proposal.weight_no = (proposal.weight_no.0 + weight).into(),
+                }
+            }
+        }
+        proposal.votes.insert(account_id, vote);
+        self.proposals.replace(id.into(), &proposal);
+    }
+
+    fn assert_can_vote(&self, proposal: &Proposal, account_id: &AccountId) {
+        if let Err(err) = self.check_can_vote(proposal, account_id) {
+            env::panic(err.as_bytes());
+        }
+    }
+
+    fn check_can_vote(&self, proposal: &Proposal, account_id: &AccountId) -> Result<(), &'static str> {
+        if proposal.status != ProposalStatus::Vote {
+            return Err("Proposal not active voting");
+        }
+        if proposal.vote_period_end <= env::block_timestamp() {
+            return Err("voting period ended");
+        }
+        if proposal.votes.contains_key(account_id) {
+            return Err("Already voted");
+        }
+        Ok(())
+    }
+
+    /// Delegates the caller's council vote to `to`, optionally only for the given proposal kinds.
+    pub fn delegate(&mut self, to: AccountId, kinds: Option<Vec<String>>) {
+        let account_id = env::predecessor_account_id();
+        assert!(self.council.contains(&account_id), "Only council can delegate");
+        assert!(self.council.contains(&to), "ERR_DELEGATE_NOT_IN_COUNCIL");
+        assert_ne!(account_id, to, "ERR_DELEGATION_CYCLE");
+        if let Some(ref kinds) = kinds {
+            assert!(!kinds.is_empty(), "ERR_NO_DELEGATION_KINDS");
+            assert!(kinds.iter().all(|kind| ProposalKind::is_kind_name(kind)), "ERR_UNKNOWN_PROPOSAL_KIND");
+        }
+
+        // follow the chain of the delegate regardless of kinds, it may not lead back to the caller
+        let mut current = to.clone();
+        while let Some(delegation) = self.delegations.get(&current) {
+            assert_ne!(delegation.delegate, account_id, "ERR_DELEGATION_CYCLE");
+            current = delegation.delegate;
+        }
+
+        self.delegations.insert(&account_id, &Delegation { delegate: to, kinds });
+    }
+
+    pub fn undelegate(&mut self) {
+        assert!(
+            self.delegations.remove(&env::predecessor_account_id()).is_some(),
+            "ERR_NOT_DELEGATED"
+        );
+    }
+
+    pub fn get_delegation(&self, account_id: AccountId) -> Option<Delegation> {
+        self.delegations.get(&account_id)
+    }
+
+    /// Number of council votes `account_id` casts on a proposal of the given kind, counting
+    /// delegated votes and 0 if its own vote is delegated.
+    pub fn get_voting_power(&self, account_id: AccountId, kind: String) -> U64 {
+        if !self.council.contains(&account_id) || self.effective_delegate(&account_id, &kind) != account_id {
+            return U64(0);
+        }
+        U64(self.delegators_of(&account_id, &kind).len() as u64 + 1)
+    }
+
+    /// Council member that ends up casting the vote of `account_id` on the given kind.
+    fn effective_delegate(&self, account_id: &AccountId, kind: &str) -> AccountId {
+        let mut current = account_id.clone();
+        while let Some(delegation) = self.delegations.get(&current) {
+            if !delegation.applies_to(kind) || !self.council.contains(&delegation.delegate) {
+                break;
+            }
+            current = delegation.delegate;
+        }
+        current
+    }
+
+    /// Council members whose vote on the given kind is cast by `account_id`.
+    fn delegators_of(&self, account_id: &AccountId, kind: &str) -> Vec<AccountId> {
+        self.delegations
+            .keys()
+            .filter(|delegator| {
+                delegator != account_id
+                    && self.council.contains(delegator)
+                    && &self.effective_delegate(delegator, kind) == account_id
+            })
+            .collect()
+    }
+
+    /// Votes on a token weighted proposal with the caller's staked governance tokens.
+    /// The stake stays locked until the vote period of the proposal ends.
+    pub fn vote_weighted(&mut self, id: U64, vote: Vote) {
+        let account_id = env::predecessor_account_id();
+        let mut proposal = self.proposals.get(id.into()).expect("No proposal with such id");
+        if proposal.voting_mode == VotingMode::Council {
+            env::panic(b"Proposal is not token weighted");
+        }
+        assert!(proposal.reveal_period_end.is_none(), "ERR_SECRET_VOTING");
+        self.assert_can_vote(&proposal, &account_id);
+        let weight = self.voting_weight(&account_id, &proposal);
+        assert!(weight > 0, "ERR_NO_VOTING_POWER");
+
+        match vote {
+            Vote::Yes => proposal.weight_yes = (proposal.weight_yes.0 + weight).into(),
+            Vote::No => proposal.weight_no = (proposal.weight_no.0 + weight).into(),
+        }
+        proposal.votes.insert(account_id.clone(), vote);
+        self.record_last_voted(&account_id, id.into(), proposal.vote_period_end);
+        let locked_until = std::cmp::max(self.vote_locks.get(&account_id).unwrap_or(0), proposal.vote_period_end);
+        self.vote_locks.insert(&account_id, &locked_until);
+        self.update_vote_status(&mut proposal);
+        proposal.last_vote = env::block_timestamp();
+        self.proposals.replace(id.into(), &proposal);
+    }
+
+    /// NEP-141 receiver. Stakes transferred governance tokens for the sender, any other token
+    /// (or the governance token sent with the `deposit` message) goes to the treasury.
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let token_id = env::predecessor_account_id();
+        if Some(&token_id) == self.staking_token.as_ref() && msg != TREASURY_DEPOSIT_MSG {
+            let staked = self.stakes.get(&sender_id).unwrap_or(0);
+            self.set_stake(&sender_id, staked + amount.0);
+            self.total_staked += amount.0;
+        } else {
+            let balance = self.ft_balances.get(&token_id).unwrap_or(0);
+            self.ft_balances.insert(&token_id, &(balance + amount.0));
+        }
+        PromiseOrValue::Value(U128(0))
+    }
+
+    /// Starts unbonding `amount` of the caller's stake, only possible once the vote periods
+    /// of all proposals they voted on have ended.
+    pub fn unstake(&mut self, amount: U128) {
+        let account_id = env::predecessor_account_id();
+        let staked = self.stakes.get(&account_id).unwrap_or(0);
+        assert!(amount.0 > 0 && amount.0 <= staked, "ERR_NOT_ENOUGH_STAKE");
+        assert!(
+            self.vote_locks.get(&account_id).unwrap_or(0) <= env::block_timestamp(),
+            "ERR_STAKE_LOCKED_BY_VOTE"
+        );
+
+        self.set_stake(&account_id, staked - amount.0);
+        self.total_staked -= amount.0;
+
+        let mut unbonding = self.unbonding.get(&account_id).unwrap_or(PendingStake {
+            amount: 0.into(),
+            available_at: 0.into(),
+        });
+        unbonding.amount = (unbonding.amount.0 + amount.0).into();
+        unbonding.available_at = (env::block_timestamp() + self.unbonding_period).into();
+        self.unbonding.insert(&account_id, &unbonding);
+    }
+
+    /// Updates the staked balance of the account and records it in its stake history.
+    fn set_stake(&mut self, account_id: &AccountId, amount: Balance) {
+        if amount == 0 {
+            self.stakes.remove(account_id);
+        } else {
+            self.stakes.insert(account_id, &amount);
+        }
+        let now = env::block_timestamp();
+        let mut checkpoints = self.stake_checkpoints.get(account_id).unwrap_or_default();
+        match checkpoints.last_mut() {
+            Some(last) if last.timestamp.0 == now => last.amount = amount.into(),
+            _ => checkpoints.push(StakeCheckpoint { timestamp: now.into(), amount: amount.into() }),
+        }
+        if checkpoints.len() > MAX_STAKE_CHECKPOINTS {
+            checkpoints.remove(0);
+        }
+        self.stake_checkpoints.insert(account_id, &checkpoints);
+    }
+
+    /// Stake of the account just before `timestamp`, 0 if that's older than the kept history.
+    fn stake_before(&self, account_id: &AccountId, timestamp: Timestamp) -> Balance {
+        self.stake_checkpoints.get(account_id)
+            .and_then(|checkpoints| {
+                checkpoints.into_iter().rev().find(|checkpoint| checkpoint.timestamp.0 < timestamp)
+            })
+            .map_or(0, |checkpoint| checkpoint.amount.0)
+    }
+
+    /// Token weighted voting power on the proposal: stake held before the proposal was created that
+    /// is still staked. Stake added later isn't part of the proposal's total weight and doesn't count.
+    fn voting_weight(&self, account_id: &AccountId, proposal: &Proposal) -> Balance {
+        std::cmp::min(
+            self.stakes.get(account_id).unwrap_or(0),
+            self.stake_before(account_id, proposal.snapshot_at.0),
+        )
+    }
+
+    /// Transfers unbonded governance tokens back to the caller.
+    pub fn withdraw_unstaked(&mut self) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let unbonding = self.unbonding.get(&account_id).expect("ERR_NOTHING_UNBONDING");
+        assert!(env::block_timestamp() >= unbonding.available_at.0, "ERR_UNBONDING_ACTIVE");
+        self.unbonding.remove(&account_id);
+        let token_id = self.staking_token.clone().expect("ERR_NO_STAKING_TOKEN");
+
+        fungible_token::ft_transfer(
+            account_id.clone(),
+            unbonding.amount,
+            None,
+            &token_id,
+            ONE_YOCTO,
+            FT_TRANSFER_GAS,
+        ).then(ext_staking::on_withdraw_unstaked(
+            account_id,
+            unbonding.amount,
+            &env::current_account_id(),
+            0,
+            FT_TRANSFER_GAS,
+        ))
+    }
+
+    pub fn on_withdraw_unstaked(&mut self, account_id: AccountId, amount: U128) {
+        utils::assert_self();
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(_) => {},
+            PromiseResult::Failed => {
+                // transfer failed, make the tokens withdrawable again right away
+                let mut unbonding = self.unbonding.get(&account_id).unwrap_or(PendingStake {
+                    amount: 0.into(),
+                    available_at: 0.into(),
+                });
+                unbonding.amount = (unbonding.amount.0 + amount.0).into();
+                self.unbonding.insert(&account_id, &unbonding);
+            },
+        };
+    }
+
+    fn proposal_success(&mut self, id: u64, proposal: &mut Proposal){
+        assert!(proposal.status.is_approved(), "Wrong status on callback");
+        proposal.status = ProposalStatus::Finalized;
+        self.proposals.replace(id, &proposal);
+
+        let bond = self.release_bond(proposal);
+        // the reward comes out of the treasury, as far as it can cover it
+        let reward = proposal.reporter_reward.map_or(0, |reward| {
+            std::cmp::min(reward.0, self.spendable_balance().saturating_sub(bond))
+        });
+        if bond + reward > 0 {
+            Promise::new(proposal.proposer.clone()).transfer(bond + reward);
+        }
+        self.pay_dispute_bond(proposal, Some(&proposal.proposer));
+    }
+
+    /// Rejects the proposal, the bond is returned unless the proposer is a reporter.
+    /// A successful disputer gets both bonds.
+    fn reject_proposal(&mut self, id: u64, proposal: &mut Proposal) -> PromiseOrValue<()> {
+        proposal.status = ProposalStatus::Rejected;
+        self.proposals.replace(id, proposal);
+        let bond = self.release_bond(proposal);
+        if let Some(ref dispute) = proposal.dispute {
+            let disputer = dispute.disputer.clone();
+            self.pay_dispute_bond(proposal, None);
+            return PromiseOrValue::Promise(Promise::new(disputer).transfer(bond));
+        }
+        if proposal.reporter_reward.is_some() {
+            return PromiseOrValue::Value(());
+        }
+        PromiseOrValue::Promise(Promise::new(proposal.proposer.clone()).transfer(bond))
+    }
+
+    /// Rejects a proposal that can't be executed through no fault of the proposer, because a
+    /// dependency failed, the council changed or its market was resolved since it passed.
+    /// All bonds are returned.
+    fn cancel_proposal(&mut self, id: u64, proposal: &mut Proposal, reason: &str) -> PromiseOrValue<()> {
+        env::log(format!("Proposal {} can't be executed: {}", id, reason).as_bytes());
+        proposal.status = ProposalStatus::Rejected;
+        self.proposals.replace(id, proposal);
+        let bond = self.release_bond(proposal);
+        self.pay_dispute_bond(proposal, None);
+        if bond == 0 {
+            return PromiseOrValue::Value(());
+        }
+        PromiseOrValue::Promise(Promise::new(proposal.proposer.clone()).transfer(bond))
+    }
+
+    /// Stops accounting the proposal's bond as locked, returns the bond.
+    fn release_bond(&mut self, proposal: &Proposal) -> Balance {
+        self.locked_bonds -= proposal.bond.0;
+        proposal.bond.0
+    }
+
+    pub fn ft_resolve_protocol_call(
+        &mut self,
+        id: U64
+    ) {
+        utils::assert_self();
+        let mut proposal = self.proposals.get(id.into()).expect("No proposal with such id");
+        proposal.executing = false;
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(value) => {
+                self.proposal_success(id.into(), &mut proposal)
+            }
+            PromiseResult::Failed => {
+                // e.g. the receiver is not registered with the token, the proposal can be retried
+                self.proposals.replace(id.into(), &proposal);
+                self.revert_external(&proposal.kind);
+            },
+        };
+    }
+
+    /// Called after each external action of a multi-action proposal, records its result.
+    /// On success the next step runs in its own receipt, so a panic there can't undo the
+    /// result or leave the proposal marked as executing. On failure the remaining actions
+    /// are not executed and the proposal can be retried.
+    pub fn on_multi_action(&mut self, id: U64, index: U64) -> PromiseOrValue<()> {
+        utils::assert_self();
+        let mut proposal = self.proposals.get(id.into()).expect("No proposal with such id");
+        let index = index.0 as usize;
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(_) => {
+                proposal.action_results[index] = ActionResult::Executed;
+                proposal.executing = false;
+                self.proposals.replace(id.into(), &proposal);
+                PromiseOrValue::Promise(ext_self::continue_actions(
+                    id,
+                    U64(index as u64 + 1),
+                    &env::current_account_id(),
+                    0,
+                    env::prepaid_gas() - ON_MULTI_ACTION_GAS,
+                ))
+            }
+            PromiseResult::Failed => {
+                proposal.action_results[index] = ActionResult::Failed;
+                proposal.executing = false;
+                self.proposals.replace(id.into(), &proposal);
+                self.revert_external(proposal.kind.actions()[index]);
+                PromiseOrValue::Value(())
+            }
+        }
+    }
+
+    /// Runs the next step of a multi-action proposal after `on_multi_action`.
+    pub fn continue_actions(&mut self, id: U64, start: U64) -> PromiseOrValue<()> {
+        utils::assert_self();
+        self.execute_actions(id, start.0 as usize)
+    }
+
+    /// Undoes the local effects of an external action whose call failed.
+    fn revert_external(&mut self, kind: &ProposalKind) {
+        if let ProposalKind::FtPayout { ref token_id, amount, .. } = *kind {
+            let balance = self.ft_balances.get(token_id).unwrap_or(0);
+            self.ft_balances.insert(token_id, &(balance + amount.0));
+        }
+    }
+
+    /// Starts the first external action from `start` that hasn't been executed yet. Once all
+    /// external actions succeeded the internal ones are applied in order and the proposal is finalized.
+    fn execute_actions(&mut self, id: U64, start: usize) -> PromiseOrValue<()> {
+        let mut proposal = self.proposals.get(id.into()).expect("No proposal with such id");
+        let actions: Vec<ProposalKind> = proposal.kind.actions().into_iter().cloned().collect();
+        let next = (start..actions.len())
+            .find(|&i| actions[i].is_external() && proposal.action_results[i] != ActionResult::Executed);
+        match next {
+            Some(index) => {
+                let remaining = actions[index + 1..].iter().filter(|action| action.is_external()).count() as u64;
+                proposal.executing = true;
+                self.proposals.replace(id.into(), &proposal);
+                let prom = self.execute_external(&actions[index]);
+                PromiseOrValue::Promise(prom.then(ext_self::on_multi_action(
+                    id,
+                    U64(index as u64),
+                    &env::current_account_id(),
+                    0,
+                    (ACTION_CALLBACK_GAS + ON_MULTI_ACTION_GAS) * (remaining + 1),
+                )))
+            }
+            None => {
+                for (index, action) in actions.iter().enumerate() {
+                    if !action.is_external() {
+                        self.execute_internal(action);
+                        proposal.action_results[index] = ActionResult::Executed;
+                    }
+                }
+                self.proposal_success(id.into(), &mut proposal);
+                PromiseOrValue::Value(())
+            }
+        }
+    }
+
+    /// Executes a proposal that passed, or rejects it and returns the bond if it didn't.
+    /// Successful proposals are only queued if their kind has a timelock.
+    pub fn execute(&mut self, id: U64) -> PromiseOrValue<()> {
+        let mut proposal = self.proposals.get(id.into()).expect("No proposal with such id");
+        assert!(
+            !proposal.status.is_finished(),
+            "Proposal already finalized"
+        );
+        assert!(!proposal.executing, "ERR_EXECUTION_IN_PROGRESS");
+        let dependencies: Vec<ProposalStatus> = proposal.depends_on
+            .iter()
+            .map(|dependency| self.proposals.get(dependency.0).unwrap().status)
+            .collect();
+        if dependencies.iter().any(|status| status.is_finished() && *status != ProposalStatus::Finalized) {
+            // not the proposer's fault, the bond is returned
+            return self.cancel_proposal(id.into(), &mut proposal, "ERR_DEPENDENCY_FAILED");
+        }
+        let approved = match proposal.status {
+            ProposalStatus::Queued { .. } => true,
+            _ => self.vote_status(&proposal).is_approved(),
+        };
+        if approved {
+            assert!(
+                dependencies.iter().all(|status| *status == ProposalStatus::Finalized),
+                "ERR_DEPENDENCY_NOT_FINALIZED"
+            );
+        }
+        if !self.resolve_status(id.into(), &mut proposal) {
+            return PromiseOrValue::Value(());
+        }
+        match proposal.status {
+            ProposalStatus::Success | ProposalStatus::Queued { .. } => {
+                let error = proposal.kind.actions()
+                    .into_iter()
+                    .find_map(|action| self.check_executable(action).err());
+                if let Some(err) = error {
+                    return self.cancel_proposal(id.into(), &mut proposal, err);
+                }
+                if let ProposalKind::ResoluteMarket { market_id, .. } = proposal.kind {
+                    // the market was resolved by a conflicting proposal
+                    if !self.claim_market(market_id.0, id.into()) {
+                        return self.cancel_proposal(id.into(), &mut proposal, "ERR_MARKET_ALREADY_RESOLVED");
+                    }
+                }
+                if let ProposalKind::MultiAction { ref actions } = proposal.kind {
+                    if proposal.action_results.is_empty() {
+                        proposal.action_results = vec![ActionResult::Pending; actions.len()];
+                    }
+                    self.proposals.replace(id.into(), &proposal);
+                    self.execute_actions(id, 0)
+                } else if proposal.kind.is_external() {
+                    proposal.executing = true;
+                    self.proposals.replace(id.into(), &proposal);
+                    let prom = self.execute_external(&proposal.kind);
+                    PromiseOrValue::Promise(prom.then(ext_self::ft_resolve_protocol_call(
+                        id,
+                        &env::current_account_id(),
+                        0,
+                        RESOLUTION_GAS,
+                    )))
+                } else {
+                    self.execute_internal(&proposal.kind);
+                    self.proposal_success(id.into(), &mut proposal);
+                    PromiseOrValue::Value(())
+                }
+            }
+            ProposalStatus::Reject => self.reject_proposal(id.into(), &mut proposal),
+            _ => {
+                env::panic(b"voting period has not expired and no majority vote yet")
+            }
+        }
+    }
+
+    /// Kept for compatibility, same as `execute`.
+    pub fn finalize_external(&mut self, id: U64) -> PromiseOrValue<()> {
+        self.execute(id)
+    }
+
+    /// Kept for compatibility, same as `execute`.
+    pub fn finalize(&mut self, id: U64) {
+        self.execute(id);
+    }
+
+    /// Calls the protocol or token contract, the result is handled by `ft_resolve_protocol_call`.
+    fn execute_external(&mut self, kind: &ProposalKind) -> Promise {
+        match *kind {
+            ProposalKind::ResoluteMarket{ ref market_id, ref payout_numerator } => {
+                // base gas + gas for each enumerator
+                let resolute_gas = match payout_numerator {
+                    Some(payout_vec) => payout_vec.len() as u64 * RESOLUTION_GAS,
+                    None => RESOLUTION_GAS
+                };
+                flux_protocol::resolute_market(
+                    *market_id,
+                    payout_numerator.clone(),
+                    &self.protocol_address,
+                    0,
+                    resolute_gas,
+                )
+            },
+            ProposalKind::SetTokenWhitelist{ ref whitelist } => {
+                flux_protocol::set_token_whitelist(
+                    whitelist.clone(),
+                    &self.protocol_address,
+                    0,
+                    RESOLUTION_GAS,
+                )
+            },
+            ProposalKind::AddTokenWhitelist{ ref to_add } => {
+                flux_protocol::add_to_token_whitelist(
+                    to_add.clone(),
+                    &self.protocol_address,
+                    0,
+                    RESOLUTION_GAS,
+                )
+            },
+            ProposalKind::SetGov{ ref new_gov } => {
+                flux_protocol::set_gov(
+                    new_gov.clone(),
+                    &self.protocol_address,
+                    0,
+                    RESOLUTION_GAS,
+                )
+            },
+            ProposalKind::PauseProtocol{ } => {
+                flux_protocol::pause(
+                    &self.protocol_address,
+                    0,
+                    RESOLUTION_GAS,
+                )
+            },
+            ProposalKind::UnpauseProtocol{ } => {
+                flux_protocol::unpause(
+                    &self.protocol_address,
+                    0,
+                    RESOLUTION_GAS,
+                )
+            },
+            ProposalKind::FtPayout{ ref token_id, ref target, amount } => {
+                let balance = self.ft_balances.get(token_id).unwrap_or(0);
+                assert!(balance >= amount.0, "ERR_INSUFFICIENT_TREASURY_BALANCE");
+                self.ft_balances.insert(token_id, &(balance - amount.0));
+                fungible_token::ft_transfer(
+                    target.clone(),
+                    amount,
+                    None,
+                    token_id,
+                    ONE_YOCTO,
+                    FT_TRANSFER_GAS,
+                )
+            },
+            _ => {
+                env::panic(b"not an external proposal")
+            }
+        }
+    }
+
+    fn execute_internal(&mut self, kind: &ProposalKind) {
+        match *kind {
+            ProposalKind::NewCouncil { ref target } => {
+                if self.council_stake > 0 {
+                    self.invitations.insert(target, &self.council_stake);
+                } else {
+                    self.add_council_member(target);
+                }
+            }
+            ProposalKind::RemoveCouncil { ref target } => {
+                self.kick_user(&target.clone());
+            }
+            ProposalKind::Payout { ref target, amount } => {
+                assert!(amount.0 <= self.spendable_balance(), "ERR_INSUFFICIENT_TREASURY_BALANCE");
+                Promise::new(target.clone()).transfer(amount.0);
+            }
+            ProposalKind::ChangeVotePeriod { vote_period } => {
+                self.vote_period = vote_period.into();
+            }
+            ProposalKind::ChangeGracePeriod { grace_period } => {
+                self.grace_period = grace_period.into();
+            }
+            ProposalKind::ChangeBond { bond } => {
+                self.bond = bond.into();
+            }
+            ProposalKind::ChangePolicy{ ref policy } => {
+                self.policy = policy.clone();
+            }
+            ProposalKind::ChangePurpose{ ref purpose } => {
+                self.purpose = purpose.clone();
+            },
+            ProposalKind::ChangeProtocolAddress{ ref address } => {
+                self.protocol_address = address.to_string();
+            },
+            ProposalKind::ChangeInactivityPolicy{ ref policy } => {
+                self.inactivity_policy = policy.clone();
+            },
+            ProposalKind::ChangeCouncilBounds{ min_council, max_council } => {
+                self.min_council = min_council.into();
+                self.max_council = max_council.into();
+            },
+            ProposalKind::ChangeCouncilStake{ stake, cooldown } => {
+                self.council_stake = stake.into();
+                self.stake_cooldown = cooldown.into();
+            },
+            ProposalKind::SlashMember{ ref target, amount } => {
+                self.slash_member(target, amount.into());
+            },
+            ProposalKind::ChangeVotingMode{ ref mode } => {
+                self.voting_mode = mode.clone();
+            },
+            ProposalKind::ChangeStaking{ ref token_id, unbonding_period } => {
+                self.staking_token = Some(token_id.clone());
+                self.unbonding_period = unbonding_period.into();
+            },
+            ProposalKind::CreateStream{ ref recipient, ref token, total, start, end, cliff } => {
+                self.create_stream(Stream {
+                    recipient: recipient.clone(),
+                    token: token.clone(),
+                    total,
+                    start,
+                    end,
+                    cliff,
+                    withdrawn: 0.into(),
+                    cancelled_at: None,
+                });
+            },
+            ProposalKind::CancelStream{ id: stream_id } => {
+                self.cancel_stream(stream_id);
+            },
+            ProposalKind::GrantBudget{ ref account_id, ref token, cap, period } => {
+                self.budgets.insert(account_id, &Budget {
+                    token: token.clone(),
+                    cap,
+                    period,
+                    period_start: env::block_timestamp().into(),
+                    spent_in_period: 0.into(),
+                });
+            },
+            ProposalKind::RevokeBudget{ ref account_id } => {
+                assert!(self.budgets.remove(account_id).is_some(), "ERR_NO_BUDGET");
+            },
+            ProposalKind::ChangeDisputeSettings{ window, bond } => {
+                self.dispute_window = window.into();
+                self.dispute_bond = bond.into();
+            },
+            ProposalKind::ChangeReporterBond{ bond, reward } => {
+                self.reporter_bond = bond.into();
+                self.reporter_reward = reward.into();
+            },
+            ProposalKind::SetSecretVoting{ ref kind, reveal_period } => {
+                match reveal_period {
+                    Some(reveal_period) => self.reveal_periods.insert(kind, &reveal_period.into()),
+                    None => self.reveal_periods.remove(kind),
+                };
+            },
+            ProposalKind::SetTimelock{ ref kind, delay } => {
+                if delay.0 > 0 {
+                    self.timelocks.insert(kind, &delay.into());
+                } else {
+                    self.timelocks.remove(kind);
+                }
+            },
+            ProposalKind::SetGuardians{ ref guardians } => {
+                self.guardians.clear();
+                for guardian in guardians {
+                    self.guardians.insert(guardian);
+                }
+            },
+            ProposalKind::SetKindGracePeriod{ ref kind, grace_period } => {
+                match grace_period {
+                    Some(grace_period) => self.kind_grace_periods.insert(kind, &grace_period.into()),
+                    None => self.kind_grace_periods.remove(kind),
+                };
+            },
+            ProposalKind::ChangeExecutionWindow{ execution_window, refund_expired_bonds } => {
+                self.execution_window = execution_window.into();
+                self.refund_expired_bonds = refund_expired_bonds;
+            },
+            _ => {
+                env::panic(b"not an internal proposal")
+            }
+        }
+    }
+
+    /// Takes a council seat offered by a passed `NewCouncil` proposal, locking the required stake.
+    #[payable]
+    pub fn accept_council_seat(&mut self) {
+        let account_id = env::predecessor_account_id();
+        let stake = self.invitations.get(&account_id).expect("ERR_NO_INVITATION");
+        assert!(env::attached_deposit() >= stake, "Not enough deposit");
+        assert!(!self.council.contains(&account_id), "ERR_ALREADY_IN_COUNCIL");
+        assert!(self.council.len() < self.max_council, "ERR_COUNCIL_TOO_LARGE");
+
+        self.invitations.remove(&account_id);
+        self.add_council_member(&account_id);
+        self.member_stakes.insert(&account_id, &stake);
+        self.locked_stakes += stake;
+
+        let refund = env::attached_deposit() - stake;
+        if refund > 0 {
+            Promise::new(account_id).transfer(refund);
+        }
+    }
+
+    /// Returns the stake of a former council member once the cooldown has passed.
+    pub fn withdraw_stake(&mut self) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let pending = self.pending_stakes.get(&account_id).expect("ERR_NO_PENDING_STAKE");
+        assert!(env::block_timestamp() >= pending.available_at.0, "ERR_STAKE_COOLDOWN_ACTIVE");
+        self.pending_stakes.remove(&account_id);
+        self.locked_stakes -= pending.amount.0;
+        Promise::new(account_id).transfer(pending.amount.into())
+    }
+
+    /// Slashes up to `amount` from the locked stake, falling back to a stake still in cooldown.
+    /// Slashed funds stay in the DAO.
+    fn slash_member(&mut self, account_id: &AccountId, amount: Balance) {
+        if let Some(stake) = self.member_stakes.get(account_id) {
+            let slashed = std::cmp::min(stake, amount);
+            self.member_stakes.insert(account_id, &(stake - slashed));
+            self.locked_stakes -= slashed;
+        } else if let Some(mut pending) = self.pending_stakes.get(account_id) {
+            let slashed = std::cmp::min(pending.amount.0, amount);
+            pending.amount = (pending.amount.0 - slashed).into();
+            self.pending_stakes.insert(account_id, &pending);
+            self.locked_stakes -= slashed;
+        } else {
+            env::panic(b"ERR_NO_MEMBER_STAKE");
+        }
+    }
+
+    pub fn exit_dao(&mut self) {
+        self.kick_user(&env::predecessor_account_id());
+    }
+
+    fn kick_user(&mut self, account_id: &AccountId) {
+        let proposalid = self.last_voted.get(account_id);
+        if !proposalid.is_none() {
+            let proposal = self.proposals.get(proposalid.unwrap()).expect("ERR_PROPOSAL_NOT_FOUND");
+
+            match proposal.kind {
+                ProposalKind::RemoveCouncil { target } => {
+                    if &target != account_id {
+                        assert!(proposal.status != ProposalStatus::Vote, "ERR_VOTING_ACTIVE");
+                    }
+                },
+                _ => {
+                    assert!(proposal.status != ProposalStatus::Vote, "ERR_VOTING_ACTIVE");
+                }
+            }
+        }
+        if let Err(err) = self.check_removal(account_id) {
+            env::panic(err.as_bytes());
+        }
+        self.council.remove(account_id);
+        self.council_since.remove(account_id);
+        self.delegations.remove(account_id);
+        self.vote_keys.remove(account_id);
+
+        if let Some(stake) = self.member_stakes.remove(account_id) {
+            let mut pending = self.pending_stakes.get(account_id).unwrap_or(PendingStake {
+                amount: 0.into(),
+                available_at: 0.into(),
+            });
+            pending.amount = (pending.amount.0 + stake).into();
+            pending.available_at = (env::block_timestamp() + self.stake_cooldown).into();
+            self.pending_stakes.insert(account_id, &pending);
+        }
+    }
+
+    /// Checks that removing the member keeps the council within its bounds.
+    fn check_removal(&self, account_id: &AccountId) -> Result<(), &'static str> {
+        if !self.council.contains(account_id) {
+            return Err("ERR_NOT_IN_COUNCIL");
+        }
+        let num_council = self.council.len();
+        if num_council <= self.min_council {
+            return Err("ERR_COUNCIL_TOO_SMALL");
+        }
+        let fixed_votes = self.max_fixed_votes();
+        if num_council >= fixed_votes && num_council - 1 < fixed_votes {
+            return Err("ERR_FIXED_VOTES_UNREACHABLE");
+        }
+        Ok(())
+    }
+
+    /// Checks that the action can still be applied to the current state. Checked when the proposal
+    /// is created and again before it's executed, as the council or staking token may change in between.
+    fn check_executable(&self, kind: &ProposalKind) -> Result<(), &'static str> {
+        let num_council = self.council.len();
+        match *kind {
+            ProposalKind::NewCouncil { .. } if num_council >= self.max_council => {
+                Err("ERR_COUNCIL_TOO_LARGE")
+            }
+            ProposalKind::RemoveCouncil { ref target } => self.check_removal(target),
+            ProposalKind::ChangeCouncilBounds { min_council, max_council } => {
+                if num_council < min_council.0 {
+                    Err("ERR_COUNCIL_TOO_SMALL")
+                } else if num_council > max_council.0 {
+                    Err("ERR_COUNCIL_TOO_LARGE")
+                } else {
+                    Ok(())
+                }
+            }
+            ProposalKind::ChangeVotingMode { mode: VotingMode::TokenWeighted { ref token_id } }
+                if Some(token_id) != self.staking_token.as_ref() => Err("ERR_NOT_STAKING_TOKEN"),
+            ProposalKind::ChangeStaking { ref token_id, .. } => match self.staking_token {
+                Some(ref staking_token) if staking_token != token_id => Err("ERR_STAKING_TOKEN_ALREADY_SET"),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Highest fixed vote count required by any policy, 0 if all policies are ratios.
+    fn max_fixed_votes(&self) -> u64 {
+        std::cmp::max(
+            RESOLUTE_POLICY.fixed_votes().unwrap_or(0),
+            self.policy.fixed_votes().unwrap_or(0),
+        )
+    }
+
+    fn add_council_member(&mut self, account_id: &AccountId) {
+        if self.council.insert(account_id) {
+            self.council_since.insert(account_id, &self.proposals.len());
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use near_sdk::MockedBlockchain;
+    use near_sdk::{testing_env, VMContext};
+
+    use super::*;
+
+    fn alice() -> AccountId {
+        "alice.near".to_string()
+    }
+    fn bob() -> AccountId {
+        "bob.near".to_string()
+    }
+    fn carol() -> AccountId {
+        "carol.near".to_string()
+    }
+    fn dave() -> AccountId {
+        "dave.near".to_string()
+    }
+
+    fn protocol_address() -> AccountId {
+        "protocol".to_string()
+    }
+
+    fn token() -> AccountId {
+        "token.near".to_string()
+    }
+
+    fn get_context(predecessor_account_id: AccountId) -> VMContext {
+        VMContext {
+            current_account_id: alice(),
+            signer_account_id: bob(),
+            signer_account_pk: vec![0, 1, 2],
+            predecessor_account_id,
+            input: vec![],
+            block_index: 0,
+            block_timestamp: 0,
+            account_balance: 0,
+            account_locked_balance: 0,
+            storage_usage: 10u64.pow(6),
+            attached_deposit: 0,
+            prepaid_gas: 10u64.pow(18),
+            random_seed: vec![0, 1, 2],
+            is_view: false,
+            output_data_receivers: vec![],
+            epoch_height: 0,
+        }
+    }
+
+    fn init() -> FluxDAO {
+        let mut dao = FluxDAO::new(
+            String::from("do cool shit"),
+            vec![alice()],
+            U128(0),
+            U64(10),
+            U64(10),
+            protocol_address()
+        );
+        dao
+    }
+
+    fn poll_finalize(contract : &mut FluxDAO, id: U64) {
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.finalize(id);
+    }
+
+    fn add_bob(contract : &mut FluxDAO) {
+        let proposal = ProposalInput {
+            description:  String::from("add bob"),
+            kind: ProposalKind::NewCouncil { target: bob() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let index:U64 = contract.add_proposal(proposal);
+        contract.vote(index, Vote::Yes);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 10000;
+        testing_env!(context);
+        contract.finalize(index);
+    }
+
+    fn add_carol(contract : &mut FluxDAO) {
+        let mut context = get_context(alice());
+        // todo remove
+        context.block_timestamp = 100;
+        context.attached_deposit = to_yocto(1000);
+        testing_env!(context);
+
+        let proposal = ProposalInput {
+            description:  String::from("add carol"),
+            kind: ProposalKind::NewCouncil{ target: carol() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let index:U64 = contract.add_proposal(proposal);
+        contract.vote(index, Vote::Yes);
+
+        // todo verify
+        let mut context = get_context(bob());
+        testing_env!(context);
+        contract.vote(index, Vote::Yes);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 20000;
+        testing_env!(context);
+        contract.finalize(index);
+    }
+
+    fn add_dave(contract : &mut FluxDAO) {
+        let mut context = get_context(alice());
+        // todo remove
+        context.block_timestamp = 100;
+        context.attached_deposit = to_yocto(1000);
+        testing_env!(context);
+
+        let proposal = ProposalInput {
+            description:  String::from("add dave"),
+            kind: ProposalKind::NewCouncil{ target: dave() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let index:U64 = contract.add_proposal(proposal);
+        contract.vote(index, Vote::Yes);
+
+        // todo verify
+        let mut context = get_context(carol());
+        testing_env!(context);
+        contract.vote(index, Vote::Yes);
+
+        // let mut context = get_context(bob());
+        // testing_env!(context);
+        // contract.vote(index, Vote::Yes);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 20000;
+        testing_env!(context);
+        contract.finalize(index);
+    }
+
+
+    #[test]
+    fn test_new() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = FluxDAO::new(
+            String::from("do cool shit"),
+            vec![alice(), bob()],
+            U128(1_000_000_u128),
+            U64(1000_u64),
+            U64(2000_u64),
+            protocol_address()
+        );
+
+        let purpose = String::from("do cool shit");
+        let bond_amount:WrappedBalance  = U128(1_000_000_u128);
+        let vote_period:WrappedDuration = U64(1000_u64);
+        let grace_period:WrappedDuration = U64(2000_u64);
+        let council = vec![alice(), bob()];
+        assert_eq!(contract.get_bond(), bond_amount);
+        assert_eq!(contract.get_vote_period(), vote_period);
+        assert_eq!(contract.get_grace_period(), grace_period);
+        assert_eq!(contract.get_council(), council);
+        assert_eq!(contract.get_num_proposals(), U64(0));
+        assert_eq!(contract.get_purpose(), purpose);
+
+        assert_eq!(contract.purpose, purpose);
+        assert_eq!(contract.bond, bond_amount.into());
+        assert_eq!(contract.vote_period, vote_period.0);
+        assert_eq!(contract.grace_period, grace_period.0);
+        assert_eq!(contract.policy.max_amount, U128(0));
+        assert_eq!(contract.council.len(), 2);
+        assert_eq!(contract.proposals.len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough deposit")]
+    fn test_add_new_council_proposal_insufficient_deposit() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(2000);
+        testing_env!(context);
+
+        let mut contract = FluxDAO::new(
+            String::from("do cool shit"),
+            vec![alice()],
+            U128(to_yocto(2)),
+            U64(0),
+            U64(0),
+            protocol_address()
+        );
+        let proposal = ProposalInput {
+            description: String::from("carol is cool"),
+            kind: ProposalKind::NewCouncil{target: carol() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(1);
+        testing_env!(context);
+        contract.add_proposal(proposal);
+    }
+
+    #[test]
+    #[should_panic(expected = "Description length is too long")]
+    fn test_add_new_council_invalid_description() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("a").repeat(281),
+            kind: ProposalKind::NewCouncil { target: carol() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+    }
+
+    #[test]
+    fn test_add_new_council_proposal() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let description = String::from("carol is cool");
+        let proposal = ProposalInput {
+            description: description.clone(),
+            kind: ProposalKind::NewCouncil{ target: carol() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+
+        // Carol (not in council) creates a proposal to include her in the counsil
+        let index:U64 = contract.add_proposal(proposal);
+        // TODO, verify contract balance in NEAR
+        assert_eq!(index, U64(0));
+        assert_eq!(contract.get_num_proposals(), U64(1));
+        let mut proposal = contract.get_proposal(U64(0));
+        assert_eq!(proposal.status, ProposalStatus::Vote);
+        assert_eq!(proposal.proposer, alice());
+        //assert_eq!(proposal.kind.target, carol());
+        assert_eq!(proposal.description, description);
+        //assert_eq!(proposal.kind, ProposalKind::NewCouncil);
+        // TODO, how to get block timestamp of tx
+        //assert_eq!(proposal.vote_period_end, timestamp+U64(1000_u64));
+        assert_eq!(proposal.vote_yes, 0);
+        assert_eq!(proposal.vote_no, 0);
+
+        context = get_context(alice());
+        testing_env!(context);
+
+        assert_eq!(contract.council.len(), 1);
+        contract.vote(U64(0), Vote::Yes);
+
+        poll_finalize(&mut contract, U64(0));
+        proposal = contract.get_proposal(U64(0));
+
+        assert_eq!(proposal.vote_yes, 1);
+        assert_eq!(proposal.vote_no, 0);
+        assert_eq!(proposal.status, ProposalStatus::Finalized);
+        assert_eq!(proposal.proposer, alice());
+       // assert_eq!(proposal.kind.target, carol());
+        assert_eq!(proposal.description, description);
+        assert_eq!(contract.council.len(), 2);
+    }
+
+    #[test]
+    fn test_exit_dao() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        add_bob(&mut contract);
+        assert_eq!(contract.council.len(), 2);
+        contract.exit_dao();
+        assert_eq!(contract.council.len(), 1);
+        // TODO test for running polls
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_COUNCIL_TOO_SMALL")]
+    fn test_exit_dao_last_member() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        contract.exit_dao();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_FIXED_VOTES_UNREACHABLE")]
+    fn test_exit_dao_resolute_policy_unreachable() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+        add_dave(&mut contract);
+        assert_eq!(contract.council.len(), 4);
+        contract.exit_dao();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_COUNCIL_TOO_LARGE")]
+    fn test_max_council() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        contract.max_council = 1;
+        add_bob(&mut contract);
+    }
+
+    #[test]
+    fn test_change_council_bounds() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("bounds"),
+            kind: ProposalKind::ChangeCouncilBounds{ min_council: U64(1), max_council: U64(5) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        poll_finalize(&mut contract, id);
+        let bounds = contract.get_council_bounds();
+        assert_eq!(bounds.min_council, U64(1));
+        assert_eq!(bounds.max_council, U64(5));
+    }
+
+    #[test]
+    fn test_council_full_before_execution() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        contract.max_council = 2;
+        let add_bob = contract.add_proposal(ProposalInput {
+            description: String::from("add bob"),
+            kind: ProposalKind::NewCouncil { target: bob() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        });
+        let add_carol = contract.add_proposal(ProposalInput {
+            description: String::from("add carol"),
+            kind: ProposalKind::NewCouncil { target: carol() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        });
+        contract.vote(add_bob, Vote::Yes);
+        contract.vote(add_carol, Vote::Yes);
+        poll_finalize(&mut contract, add_bob);
+        poll_finalize(&mut contract, add_carol);
+
+        assert_eq!(contract.council.len(), 2);
+        assert_eq!(contract.get_proposal(add_carol).status, ProposalStatus::Rejected);
+        assert_eq!(contract.locked_bonds, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_COUNCIL_BOUNDS")]
+    fn test_change_council_bounds_invalid() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("bounds"),
+            kind: ProposalKind::ChangeCouncilBounds{ min_council: U64(5), max_council: U64(2) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only council can create proposals")]
+    fn test_proposal_outside_council() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let mut context = get_context(bob());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let description = String::from("bob sucks");
+        let proposal = ProposalInput {
+            description: description.clone(),
+            kind: ProposalKind::NewCouncil{target: bob() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let index:U64 = contract.add_proposal(proposal);
+        assert_eq!(index, U64(0));
+    }
+
+    #[test]
+    fn test_remove_council_proposal() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+        let description = String::from("bob sucks");
+        let proposal = ProposalInput {
+            description: description.clone(),
+            kind: ProposalKind::RemoveCouncil{target: bob()},
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let index:U64 = contract.add_proposal(proposal);
+
+        assert_eq!(contract.council.len(), 3);
+
+        let mut context = get_context(alice());
+        testing_env!(context);
+        contract.vote(index, Vote::Yes);
+
+        let mut context = get_context(carol());
+
+        testing_env!(context);
+        contract.vote(index, Vote::Yes);
+        context = get_context(alice());
+        // TODO, is sending near expected in this case
+        // this amount pays out the exit bond
+        context.attached_deposit = to_yocto(5000);
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.finalize(index);
+
+        assert_eq!(contract.council.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_council_proposal_voteself() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        assert_eq!(contract.council.len(), 1);
+        add_bob(&mut contract);
+        assert_eq!(contract.council.len(), 2);
+        add_carol(&mut contract);
+        let description = String::from("bob sucks");
+        let proposal = ProposalInput {
+            description: description.clone(),
+            kind: ProposalKind::RemoveCouncil{target:bob()},
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let index:U64 = contract.add_proposal(proposal);
+        assert_eq!(contract.council.len(), 3);
+
+        let mut context = get_context(alice());
+        testing_env!(context);
+        contract.vote(index, Vote::Yes);
+
+        let mut context = get_context(bob());
+        testing_env!(context);
+        contract.vote(index, Vote::Yes);
+
+        context = get_context(alice());
+        // todo check deposit
+        context.attached_deposit = to_yocto(5000);
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.finalize(index);
+
+        assert_eq!(contract.council.len(), 2);
+    }
+
+    #[test]
+    fn test_payout_proposal() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        // storage of 10^6 bytes locks 10 NEAR
+        context.account_balance = to_yocto(20);
+        testing_env!(context);
+
+        let mut contract = init();
+        let description = String::from("bob payout");
+        let proposal = ProposalInput {
+            description: description.clone(),
+            kind: ProposalKind::Payout{ target: bob(), amount: U128(to_yocto(1)) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+        contract.vote(U64(0), Vote::Yes);
+        // TODO, check balance
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INSUFFICIENT_TREASURY_BALANCE")]
+    fn test_payout_proposal_exceeds_treasury() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5);
+        context.account_balance = to_yocto(20);
+        testing_env!(context);
+
+        let mut contract = init();
+        contract.bond = to_yocto(5);
+        let proposal = ProposalInput {
+            description: String::from("bob payout"),
+            kind: ProposalKind::Payout{ target: bob(), amount: U128(to_yocto(6)) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+    }
+
+    #[test]
+    fn test_treasury_summary() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5);
+        context.account_balance = to_yocto(20);
+        testing_env!(context);
+
+        let mut contract = init();
+        contract.bond = to_yocto(5);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        let summary = contract.get_treasury_summary();
+        assert_eq!(summary.storage_locked, U128(to_yocto(10)));
+        assert_eq!(summary.bonds_locked, U128(to_yocto(5)));
+        assert_eq!(summary.spendable, U128(to_yocto(5)));
+
+        contract.vote(id, Vote::Yes);
+        poll_finalize(&mut contract, id);
+        assert_eq!(contract.get_treasury_summary().bonds_locked, U128(0));
+    }
+
+    fn create_near_stream(contract : &mut FluxDAO) {
+        let proposal = ProposalInput {
+            description: String::from("stream to bob"),
+            kind: ProposalKind::CreateStream{
+                recipient: bob(),
+                token: None,
+                total: U128(1000),
+                start: U64(1000),
+                end: U64(2000),
+                cliff: U64(1200)
+            },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        let mut context = get_context(alice());
+        context.block_timestamp = 1000;
+        context.account_balance = to_yocto(20);
+        testing_env!(context);
+        contract.finalize(id);
+        assert_eq!(contract.get_num_streams(), U64(1));
+        assert_eq!(contract.get_treasury_summary().streams_locked, U128(1000));
+    }
+
+    #[test]
+    fn test_stream_withdraw() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        create_near_stream(&mut contract);
+
+        let mut context = get_context(bob());
+        context.block_timestamp = 1100;
+        testing_env!(context);
+        // before the cliff
+        assert_eq!(contract.get_stream_balance(U64(0)).vested, U128(0));
+
+        let mut context = get_context(bob());
+        context.block_timestamp = 1500;
+        testing_env!(context);
+        assert_eq!(contract.get_stream_balance(U64(0)).withdrawable, U128(500));
+        contract.withdraw_stream(U64(0));
+        let balance = contract.get_stream_balance(U64(0));
+        assert_eq!(balance.withdrawn, U128(500));
+        assert_eq!(balance.withdrawable, U128(0));
+        assert_eq!(contract.get_treasury_summary().streams_locked, U128(500));
+    }
+
+    #[test]
+    fn test_cancel_stream() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        create_near_stream(&mut contract);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 1250;
+        testing_env!(context);
+        let proposal = ProposalInput {
+            description: String::from("cancel stream"),
+            kind: ProposalKind::CancelStream{ id: U64(0) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        let mut context = get_context(alice());
+        context.block_timestamp = 1300;
+        testing_env!(context);
+        contract.finalize(id);
+        assert_eq!(contract.get_treasury_summary().streams_locked, U128(300));
+
+        let mut context = get_context(bob());
+        context.block_timestamp = 5000;
+        testing_env!(context);
+        assert_eq!(contract.get_stream_balance(U64(0)).vested, U128(300));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_STREAM_RECIPIENT")]
+    fn test_stream_withdraw_not_recipient() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        create_near_stream(&mut contract);
+        contract.withdraw_stream(U64(0));
+    }
+
+    fn grant_bob_budget(contract : &mut FluxDAO) {
+        let proposal = ProposalInput {
+            description: String::from("budget for bob"),
+            kind: ProposalKind::GrantBudget{
+                account_id: bob(),
+                token: None,
+                cap: U128(100),
+                period: U64(1000)
+            },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        poll_finalize(contract, id);
+    }
+
+    #[test]
+    fn test_spend_from_budget() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        grant_bob_budget(&mut contract);
+
+        let mut context = get_context(bob());
+        context.block_timestamp = 50500;
+        context.account_balance = to_yocto(20);
+        testing_env!(context);
+        contract.spend_from_budget(carol(), U128(60), String::from("design work"));
+        contract.spend_from_budget(carol(), U128(40), String::from("more design work"));
+        assert_eq!(contract.get_budget(bob()).unwrap().spent_in_period, U128(100));
+        let spends = contract.get_budget_spends(U64(0), U64(10));
+        assert_eq!(spends.len(), 2);
+        assert_eq!(spends[0].target, carol());
+        assert_eq!(spends[0].memo, String::from("design work"));
+
+        // next period
+        let mut context = get_context(bob());
+        context.block_timestamp = 51000;
+        context.account_balance = to_yocto(20);
+        testing_env!(context);
+        contract.spend_from_budget(carol(), U128(100), String::from("next month"));
+        let budget = contract.get_budget(bob()).unwrap();
+        assert_eq!(budget.period_start, U64(51000));
+        assert_eq!(budget.spent_in_period, U128(100));
+        let spends = contract.get_budget_spends(U64(0), U64(10));
+        assert_eq!(spends[0].period_start, U64(50000));
+        assert_eq!(spends[2].period_start, U64(51000));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BUDGET_EXCEEDED")]
+    fn test_spend_from_budget_exceeded() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        grant_bob_budget(&mut contract);
+
+        let mut context = get_context(bob());
+        context.block_timestamp = 50500;
+        context.account_balance = to_yocto(20);
+        testing_env!(context);
+        contract.spend_from_budget(carol(), U128(60), String::from("design work"));
+        contract.spend_from_budget(carol(), U128(41), String::from("more design work"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NO_BUDGET")]
+    fn test_revoke_budget() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        grant_bob_budget(&mut contract);
+
+        let proposal = ProposalInput {
+            description: String::from("revoke budget"),
+            kind: ProposalKind::RevokeBudget{ account_id: bob() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        let mut context = get_context(alice());
+        context.block_timestamp = 60000;
+        testing_env!(context);
+        contract.finalize(id);
+
+        let mut context = get_context(bob());
+        context.block_timestamp = 60000;
+        context.account_balance = to_yocto(20);
+        testing_env!(context);
+        contract.spend_from_budget(carol(), U128(1), String::from("x"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_UNKNOWN_PROPOSAL_KIND")]
+    fn test_timelock_unknown_kind() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("timelock"),
+            kind: ProposalKind::SetTimelock{ kind: String::from("Payouts"), delay: U64(1000) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+    }
+
+    fn queue_purpose_change(contract : &mut FluxDAO) -> U64 {
+        contract.timelocks.insert(&String::from("ChangePurpose"), &1000);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        poll_finalize(contract, id);
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Queued { eta: U64(51000) });
+        assert_eq!(contract.purpose, String::from("do cool shit"));
+        id
+    }
+
+    #[test]
+    fn test_timelock_queue() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let id = queue_purpose_change(&mut contract);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 51000;
+        testing_env!(context);
+        contract.finalize(id);
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Finalized);
+        assert_eq!(contract.purpose, String::from("y"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TIMELOCK_ACTIVE")]
+    fn test_timelock_active() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let id = queue_purpose_change(&mut contract);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50999;
+        testing_env!(context);
+        contract.finalize(id);
+    }
+
+    #[test]
+    fn test_veto_queued() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        contract.guardians.insert(&carol());
+        let id = queue_purpose_change(&mut contract);
+
+        let mut context = get_context(carol());
+        context.block_timestamp = 50500;
+        testing_env!(context);
+        contract.veto_queued(id);
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Vetoed);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only guardians can veto")]
+    fn test_veto_queued_not_guardian() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let id = queue_purpose_change(&mut contract);
+        contract.veto_queued(id);
+    }
+
+    #[test]
+    fn test_vote_period_proposal() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let description = String::from("vote period");
+        let proposal = ProposalInput {
+            description: description.clone(),
+            kind: ProposalKind::ChangeVotePeriod{ vote_period: U64(1) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+        assert_eq!(contract.get_vote_period(), U64(10));
+        contract.vote(U64(0), Vote::Yes);
+
+        poll_finalize(&mut contract, U64(0));
+        assert_eq!(contract.get_vote_period(), U64(1));
+        // TODO, check balance
+    }
+
+    #[test]
+    fn test_grace_period_proposal() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("grace period"),
+            kind: ProposalKind::ChangeGracePeriod{ grace_period: U64(100) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+        assert_eq!(contract.get_grace_period(), U64(10));
+        contract.vote(U64(0), Vote::Yes);
+
+        poll_finalize(&mut contract, U64(0));
+        assert_eq!(contract.get_grace_period(), U64(100));
+        assert_eq!(contract.get_kind_grace_period(String::from("ChangePurpose")), U64(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_GRACE_PERIOD_TOO_LONG")]
+    fn test_grace_period_proposal_too_long() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("grace period"),
+            kind: ProposalKind::ChangeGracePeriod{ grace_period: U64(MAX_GRACE_PERIOD + 1) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+    }
+
+    #[test]
+    fn test_change_bond_proposal() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let description = String::from("bond");
+        let proposal = ProposalInput {
+            description: description.clone(),
+            kind: ProposalKind::ChangeBond{ bond: U128(1) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+        assert_eq!(contract.get_bond(), U128(0));
+        contract.vote(U64(0), Vote::Yes);
+
+        poll_finalize(&mut contract, U64(0));
+        assert_eq!(contract.get_bond(), U128(1));
+        // TODO, check balance
+    }
+
+    #[test]
+    fn test_change_policy_proposal() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let description = String::from("policy");
+        let policy = PolicyItem {
+            max_amount: 100.into(),
+            votes: NumOrRatio::Ratio(1, 2),
+        };
+        let proposal = ProposalInput {
+            description: description.clone(),
+            kind: ProposalKind::ChangePolicy{ policy },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+        assert_eq!(contract.policy.max_amount, U128(0));
+        contract.vote(U64(0), Vote::Yes);
+
+        poll_finalize(&mut contract, U64(0));
+        assert_eq!(contract.policy.max_amount, U128(100));
+    }
+
+    #[test]
+    fn test_change_purpose_proposal() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let purpose = String::from("do cool shit");
+        let mut contract = init();
+        let description = String::from("do cooler shit");
+        let proposal = ProposalInput {
+            description: description.clone(),
+            kind: ProposalKind::ChangePurpose{ purpose: description.clone() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+        assert_eq!(contract.purpose, purpose);
+        contract.vote(U64(0), Vote::Yes);
+
+        poll_finalize(&mut contract, U64(0));
+        assert_eq!(contract.purpose, description);
+    }
+
+    #[test]
+    fn test_change_bond_proposal_fail() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let description = String::from("bond");
+        let proposal = ProposalInput {
+            description: description.clone(),
+            kind: ProposalKind::ChangeBond{ bond: U128(1) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let index:U64 = contract.add_proposal(proposal);
+
+        assert_eq!(contract.get_bond(), U128(0));
+        contract.vote(index, Vote::No);
+
+        poll_finalize(&mut contract, index);
+        let p:Proposal = contract.get_proposal(index);
+        assert_eq!(p.status, ProposalStatus::Rejected);
+        assert_eq!(contract.get_bond(), U128(0));
+        // TODO, check balance
+    }
+
+    #[test]
+    #[should_panic(expected = "Only council can vote")]
+    fn test_no_council_vote() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose:String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+
+        let mut context = get_context(bob());
+        testing_env!(context);
+        contract.vote(U64(0), Vote::Yes);
+    }
+
+    #[test]
+    #[should_panic(expected = "No proposal with such id")]
+    fn test_no_proposal_vote() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        contract.vote(U64(0), Vote::Yes);
+    }
+
+    #[test]
+    #[should_panic(expected = "Proposal not active voting")]
+    fn test_proposal_already_finalized() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose:String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+        contract.vote(U64(0), Vote::Yes);
+
+        poll_finalize(&mut contract, U64(0));
+        contract.vote(U64(0), Vote::Yes);
+    }
+
+    #[test]
+    #[should_panic(expected = "Already voted")]
+    fn test_already_voted() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let mut contract = init();
+        add_bob(&mut contract);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose:String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+        contract.vote(U64(1), Vote::Yes);
+        contract.vote(U64(1), Vote::Yes);
+    }
+
+    #[test]
+    fn test_change_protocol_address() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let protocol_new : AccountId = "protocol2".to_string();
+        let mut contract = init();
+        assert_eq!(contract.protocol_address, protocol_address());
+        let proposal = ProposalInput {
+            description: String::from("change protocol address"),
+            kind: ProposalKind::ChangeProtocolAddress{ address: protocol_new.clone() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+        contract.vote(U64(0), Vote::Yes);
+        poll_finalize(&mut contract, U64(0));
+        assert_eq!(contract.protocol_address, protocol_new.clone());
+    }
+
+    #[test]
+    #[should_panic(expected = "Grace period active")]
+    fn test_grace_period_active() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+
+        let protocol_new : AccountId = "protocol".to_string();
+        let mut contract = init();
+        assert_eq!(contract.protocol_address, protocol_address());
+        let proposal = ProposalInput {
+            description: String::from("change protocol address"),
+            kind: ProposalKind::ChangeProtocolAddress{ address: protocol_new.clone() },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+        contract.vote(U64(0), Vote::Yes);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 5;
+        testing_env!(context);
+        contract.finalize(U64(0));
+    }
+
+    #[test]
+    fn test_grace_period_from_decision() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        add_bob(&mut contract);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 20000;
+        testing_env!(context);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        assert_eq!(contract.get_proposal(id).decided_at, None);
+
+        let mut context = get_context(bob());
+        context.block_timestamp = 20005;
+        testing_env!(context);
+        contract.vote(id, Vote::Yes);
+        assert_eq!(contract.get_proposal(id).decided_at, Some(20005));
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 20015;
+        testing_env!(context);
+        contract.finalize(id);
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Finalized);
+    }
+
+    #[test]
+    fn test_rejection_without_grace_period() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 10;
+        testing_env!(context);
+        contract.finalize(id);
+        let p = contract.get_proposal(id);
+        assert_eq!(p.status, ProposalStatus::Rejected);
+        assert_eq!(p.decided_at, Some(10));
+    }
+
+    #[test]
+    fn test_success_first_seen_on_execution() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+        add_dave(&mut contract);
+
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        context.block_timestamp = 60000;
+        testing_env!(context);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        let mut context = get_context(bob());
+        context.block_timestamp = 60000;
+        testing_env!(context);
+        contract.vote(id, Vote::Yes);
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Vote);
+
+        // 2 of 3 votes pass once dave is gone
+        contract.council.remove(&dave());
+        let mut context = get_context(alice());
+        context.block_timestamp = 70000;
+        testing_env!(context);
+        contract.finalize(id);
+        let p = contract.get_proposal(id);
+        assert_eq!(p.status, ProposalStatus::Finalized);
+        assert_eq!(p.decided_at, Some(60010));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_UNKNOWN_PROPOSAL_KIND")]
+    fn test_kind_grace_period_unknown_kind() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("grace"),
+            kind: ProposalKind::SetKindGracePeriod{ kind: String::from("changePurpose"), grace_period: None },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+    }
+
+    #[test]
+    #[should_panic(expected = "Grace period active")]
+    fn test_kind_grace_period() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("grace"),
+            kind: ProposalKind::SetKindGracePeriod{ kind: String::from("ChangePurpose"), grace_period: Some(U64(100)) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        poll_finalize(&mut contract, id);
+        assert_eq!(contract.get_kind_grace_period(String::from("ChangePurpose")), U64(100));
+        assert_eq!(contract.get_kind_grace_period(String::from("PauseProtocol")), U64(0));
+
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        let mut context = get_context(alice());
+        context.block_timestamp = 50050;
+        testing_env!(context);
+        contract.finalize(id);
+    }
+
+    #[test]
+    fn test_pause_protocol() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        assert_eq!(contract.protocol_address, protocol_address());
+        let proposal = ProposalInput {
+            description: String::from("pause protocol"),
+            kind: ProposalKind::PauseProtocol{ },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(U64(0), Vote::Yes);
+        // dont chagnge block number after voting (e.g. no grace period)
+        contract.finalize_external(id);
+    }
+
+    #[test]
+    fn test_unpause_protocol() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        assert_eq!(contract.protocol_address, protocol_address());
+        let proposal = ProposalInput {
+            description: String::from("pause protocol"),
+            kind: ProposalKind::UnpauseProtocol{ },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(U64(0), Vote::Yes);
+        // dont chagnge block number after voting (e.g. no grace period)
+        contract.finalize_external(id);
+    }
+
+    #[test]
+    fn test_execute_internal_and_external() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("pause protocol"),
+            kind: ProposalKind::PauseProtocol{ },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let pause_id = contract.add_proposal(proposal);
+        contract.vote(pause_id, Vote::Yes);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let purpose_id = contract.add_proposal(proposal);
+        contract.vote(purpose_id, Vote::Yes);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        // external call, finalized by the callback
+        contract.finalize(pause_id);
+        assert_eq!(contract.get_proposal(pause_id).status, ProposalStatus::Success);
+        contract.execute(purpose_id);
+        assert_eq!(contract.get_proposal(purpose_id).status, ProposalStatus::Finalized);
+        assert_eq!(contract.purpose, String::from("y"));
+    }
+
+    #[test]
+    fn test_multi_action() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("internal actions"),
+            kind: ProposalKind::MultiAction{ actions: vec![
+                ProposalKind::ChangePurpose{ purpose: String::from("y") },
+                ProposalKind::ChangeBond{ bond: U128(5) },
+            ] },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let internal_id = contract.add_proposal(proposal);
+        contract.vote(internal_id, Vote::Yes);
+        let proposal = ProposalInput {
+            description: String::from("unpause with new address"),
+            kind: ProposalKind::MultiAction{ actions: vec![
+                ProposalKind::ChangeProtocolAddress{ address: String::from("new_protocol.near") },
+                ProposalKind::UnpauseProtocol{ },
+            ] },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let external_id = contract.add_proposal(proposal);
+        contract.vote(external_id, Vote::Yes);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.execute(internal_id);
+        let proposal = contract.get_proposal(internal_id);
+        assert_eq!(proposal.status, ProposalStatus::Finalized);
+        assert_eq!(proposal.action_results, vec![ActionResult::Executed, ActionResult::Executed]);
+        assert_eq!(contract.purpose, String::from("y"));
+        assert_eq!(contract.bond, 5);
+
+        // the internal action waits for the external one
+        contract.execute(external_id);
+        let proposal = contract.get_proposal(external_id);
+        assert_eq!(proposal.status, ProposalStatus::Success);
+        assert_eq!(proposal.action_results, vec![ActionResult::Pending, ActionResult::Pending]);
+        assert!(proposal.executing);
+        assert_eq!(contract.protocol_address, protocol_address());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_EXECUTION_IN_PROGRESS")]
+    fn test_multi_action_executed_twice() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("unpause"),
+            kind: ProposalKind::MultiAction{ actions: vec![
+                ProposalKind::UnpauseProtocol{ },
+                ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            ] },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.execute(id);
+        contract.execute(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_ACTION_NOT_ALLOWED")]
+    fn test_multi_action_nested() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("nested"),
+            kind: ProposalKind::MultiAction{ actions: vec![
+                ProposalKind::MultiAction{ actions: vec![] },
+            ] },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+    }
+
+    #[test]
+    fn test_proposal_dependencies() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let first = contract.add_proposal(proposal);
+        contract.vote(first, Vote::Yes);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangeBond{ bond: U128(5) },
+            depends_on: vec![first],
+            link: None,
+            content_hash: None,
+        };
+        let second = contract.add_proposal(proposal);
+        contract.vote(second, Vote::Yes);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("z") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let unvoted = contract.add_proposal(proposal);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangeBond{ bond: U128(6) },
+            depends_on: vec![unvoted],
+            link: None,
+            content_hash: None,
+        };
+        let dependent = contract.add_proposal(proposal);
+        contract.vote(dependent, Vote::Yes);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.execute(first);
+        contract.execute(second);
+        assert_eq!(contract.get_proposal(second).status, ProposalStatus::Finalized);
+        assert_eq!(contract.bond, 5);
+
+        // rejected dependency rejects the dependent proposal and returns its bond
+        contract.execute(unvoted);
+        contract.execute(dependent);
+        assert_eq!(contract.get_proposal(dependent).status, ProposalStatus::Rejected);
+        assert_eq!(contract.bond, 5);
+        assert_eq!(contract.locked_bonds, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DEPENDENCY_NOT_FINALIZED")]
+    fn test_proposal_dependency_not_finalized() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let first = contract.add_proposal(proposal);
+        contract.vote(first, Vote::Yes);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangeBond{ bond: U128(5) },
+            depends_on: vec![first],
+            link: None,
+            content_hash: None,
+        };
+        let second = contract.add_proposal(proposal);
+        contract.vote(second, Vote::Yes);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.execute(second);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DEPENDENCY_NOT_FOUND")]
+    fn test_proposal_dependency_not_found() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![U64(0)],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DUPLICATE_DEPENDENCY")]
+    fn test_proposal_duplicate_dependency() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let first = contract.add_proposal(ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        });
+        contract.add_proposal(ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("z") },
+            depends_on: vec![first, first],
+            link: None,
+            content_hash: None,
+        });
+    }
+
+    #[test]
+    fn test_proposal_expiry() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("window"),
+            kind: ProposalKind::ChangeExecutionWindow{ execution_window: U64(MIN_EXECUTION_WINDOW), refund_expired_bonds: false },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        poll_finalize(&mut contract, id);
+        assert_eq!(contract.get_execution_window(), U64(MIN_EXECUTION_WINDOW));
+        assert!(!contract.get_refund_expired_bonds());
+
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+
+        // executable from 50010 after the grace period, the window ends a day later
+        let mut context = get_context(alice());
+        context.block_timestamp = 50010 + MIN_EXECUTION_WINDOW;
+        testing_env!(context);
+        contract.execute(id);
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Expired);
+        assert_eq!(contract.purpose, String::from("do cool shit"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_EXECUTION_WINDOW_TOO_SHORT")]
+    fn test_execution_window_too_short() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("window"),
+            kind: ProposalKind::ChangeExecutionWindow{ execution_window: U64(1000), refund_expired_bonds: true },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.add_proposal(proposal);
+    }
+
+    #[test]
+    fn test_amend_proposal() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        context.block_timestamp = 20000;
+        testing_env!(context);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 20005;
+        testing_env!(context);
+        let proposal = ProposalInput {
+            description: String::from("fixed typo"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("z") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.amend_proposal(id, proposal);
+        let proposal = contract.get_proposal(id);
+        assert_eq!(proposal.description, String::from("fixed typo"));
+        assert_eq!(proposal.vote_yes, 0);
+        assert!(proposal.votes.is_empty());
+        assert_eq!(proposal.vote_period_end, 20015);
+        assert_eq!(proposal.amendments.len(), 1);
+        assert_eq!(proposal.amendments[0].description, String::from("x"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the proposer can amend a proposal")]
+    fn test_amend_proposal_not_proposer() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        add_bob(&mut contract);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+
+        let context = get_context(bob());
+        testing_env!(context);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("z") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.amend_proposal(id, proposal);
+    }
+
+    #[test]
+    fn test_proposal_metadata() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let hash = "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08";
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: Some(String::from("https://forum.flux.xyz/t/1")),
+            content_hash: Some(String::from(hash)),
+        };
+        let id = contract.add_proposal(proposal);
+        let proposal = contract.get_proposal(id);
+        assert_eq!(proposal.link, Some(String::from("https://forum.flux.xyz/t/1")));
+        assert_eq!(proposal.content_hash, Some(hash.to_lowercase()));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_CONTENT_HASH")]
+    fn test_proposal_invalid_content_hash() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: Some(String::from("not a hash")),
+        };
+        contract.add_proposal(proposal);
+    }
+
+    #[test]
+    fn test_commit_reveal_vote() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("secret"),
+            kind: ProposalKind::SetSecretVoting{ kind: String::from("ChangePurpose"), reveal_period: Some(U64(10)) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        poll_finalize(&mut contract, id);
+        assert_eq!(contract.get_reveal_period(String::from("ChangePurpose")), Some(U64(10)));
+
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.commit_vote(id, utils::vote_commitment(id.0, &alice(), Vote::Yes, "salt"));
+        assert!(contract.get_proposal(id).votes.is_empty());
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50015;
+        testing_env!(context);
+        contract.reveal_vote(id, Vote::Yes, String::from("salt"));
+        let proposal = contract.get_proposal(id);
+        assert_eq!(proposal.vote_yes, 1);
+        assert_eq!(proposal.status, ProposalStatus::Vote);
+
+        // executable after the reveal window and the grace period
+        let mut context = get_context(alice());
+        context.block_timestamp = 50030;
+        testing_env!(context);
+        contract.execute(id);
+        let proposal = contract.get_proposal(id);
+        assert_eq!(proposal.status, ProposalStatus::Finalized);
+        assert_eq!(proposal.decided_at, Some(50020));
+        assert_eq!(contract.purpose, String::from("y"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_COMMITMENT_MISMATCH")]
+    fn test_reveal_copied_commitment() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        add_bob(&mut contract);
+        contract.reveal_periods.insert(&String::from("ChangePurpose"), &10);
+
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        let commitment = utils::vote_commitment(id.0, &alice(), Vote::Yes, "salt");
+        contract.commit_vote(id, commitment.clone());
+
+        // bob copies alice's commitment without knowing her vote
+        let mut context = get_context(bob());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.commit_vote(id, commitment);
+        let mut context = get_context(bob());
+        context.block_timestamp = 50015;
+        testing_env!(context);
+        contract.reveal_vote(id, Vote::Yes, String::from("salt"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_COMMITMENT_MISMATCH")]
+    fn test_reveal_vote_mismatch() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let proposal = ProposalInput {
+            description: String::from("secret"),
+            kind: ProposalKind::SetSecretVoting{ kind: String::from("ChangePurpose"), reveal_period: Some(U64(10)) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        poll_finalize(&mut contract, id);
+
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.commit_vote(id, utils::vote_commitment(id.0, &alice(), Vote::No, "salt"));
 
-    fn alice() -> AccountId {
-        "alice.near".to_string()
-    }
-    fn bob() -> AccountId {
-        "bob.near".to_string()
-    }
-    fn carol() -> AccountId {
-        "carol.near".to_string()
-    }
-    fn dave() -> AccountId {
-        "dave.near".to_string()
+        let mut context = get_context(alice());
+        context.block_timestamp = 50015;
+        testing_env!(context);
+        contract.reveal_vote(id, Vote::Yes, String::from("salt"));
     }
 
-    fn protocol_address() -> AccountId {
-        "protocol".to_string()
+    fn vote_keypair() -> ed25519_dalek::Keypair {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[7; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        ed25519_dalek::Keypair { secret, public }
     }
 
-    fn get_context(predecessor_account_id: AccountId) -> VMContext {
-        VMContext {
-            current_account_id: alice(),
-            signer_account_id: bob(),
-            signer_account_pk: vec![0, 1, 2],
-            predecessor_account_id,
-            input: vec![],
-            block_index: 0,
-            block_timestamp: 0,
-            account_balance: 0,
-            account_locked_balance: 0,
-            storage_usage: 10u64.pow(6),
-            attached_deposit: 0,
-            prepaid_gas: 10u64.pow(18),
-            random_seed: vec![0, 1, 2],
-            is_view: false,
-            output_data_receivers: vec![],
-            epoch_height: 0,
-        }
+    fn vote_public_key(keypair: &ed25519_dalek::Keypair) -> Base58PublicKey {
+        let mut bytes = vec![0];
+        bytes.extend_from_slice(keypair.public.as_bytes());
+        Base58PublicKey(bytes)
     }
 
-    fn init() -> FluxDAO {
-        let mut dao = FluxDAO::new(
-            String::from("do cool shit"),
-            vec![alice()],
-            U128(0),
-            U64(10),
-            U64(10),
-            protocol_address()
-        );
-        dao
-    }
+    #[test]
+    fn test_vote_with_signature() {
+        use ed25519_dalek::Signer;
 
-    fn poll_finalize(contract : &mut FluxDAO, id: U64) {
         let mut context = get_context(alice());
-        context.block_timestamp = 50000;
+        context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-        contract.finalize(id);
+        let mut contract = init();
+        let keypair = vote_keypair();
+        contract.add_vote_key(vote_public_key(&keypair));
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+
+        // submitted by a relayer
+        let context = get_context(bob());
+        testing_env!(context);
+        let message = format!("{}:{}:Yes:{}:1", env::current_account_id(), id.0, alice());
+        let signature = keypair.sign(message.as_bytes());
+        contract.vote_with_signature(
+            id,
+            Vote::Yes,
+            alice(),
+            vote_public_key(&keypair),
+            Base64VecU8(signature.to_bytes().to_vec()),
+            U64(1)
+        );
+        let proposal = contract.get_proposal(id);
+        assert_eq!(proposal.votes.get(&alice()), Some(&Vote::Yes));
+        assert_eq!(contract.get_vote_nonce(alice()), U64(1));
     }
 
-    fn add_bob(contract : &mut FluxDAO) {
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_SIGNATURE")]
+    fn test_vote_with_invalid_signature() {
+        use ed25519_dalek::Signer;
+
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        let keypair = vote_keypair();
+        contract.add_vote_key(vote_public_key(&keypair));
         let proposal = ProposalInput {
-            description:  String::from("add bob"),
-            kind: ProposalKind::NewCouncil { target: bob() },
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        let index:U64 = contract.add_proposal(proposal);
-        contract.vote(index, Vote::Yes);
+        let id = contract.add_proposal(proposal);
+
+        // signed a No vote
+        let message = format!("{}:{}:No:{}:1", env::current_account_id(), id.0, alice());
+        let signature = keypair.sign(message.as_bytes());
+        contract.vote_with_signature(
+            id,
+            Vote::Yes,
+            alice(),
+            vote_public_key(&keypair),
+            Base64VecU8(signature.to_bytes().to_vec()),
+            U64(1)
+        );
+    }
 
+    #[test]
+    fn test_vote_batch() {
         let mut context = get_context(alice());
-        context.block_timestamp = 10000;
+        context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-        contract.finalize(index);
+        let mut contract = init();
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        context.block_timestamp = 30000;
+        testing_env!(context);
+        let mut ids = vec![];
+        for market_id in 0..3 {
+            let proposal = ProposalInput {
+                description: String::from("resolute"),
+                kind: ProposalKind::ResoluteMarket{ market_id: U64(market_id), payout_numerator: None },
+                depends_on: vec![],
+                link: None,
+                content_hash: None,
+            };
+            ids.push(contract.add_proposal(proposal));
+        }
+        contract.vote(ids[1], Vote::Yes);
+
+        let results = contract.vote_batch(vec![
+            (ids[0], Vote::Yes),
+            (ids[1], Vote::Yes),
+            (ids[2], Vote::No),
+            (U64(100), Vote::Yes),
+        ]);
+        assert!(results[0].error.is_none());
+        assert_eq!(results[1].error, Some(String::from("Already voted")));
+        assert!(results[2].error.is_none());
+        assert_eq!(results[3].error, Some(String::from("No proposal with such id")));
+        assert_eq!(contract.get_proposal(ids[0]).vote_yes, 1);
+        assert_eq!(contract.get_proposal(ids[2]).vote_no, 1);
+        assert_eq!(contract.last_voted.get(&alice()), Some(ids[2].0));
     }
 
-    fn add_carol(contract : &mut FluxDAO) {
+    #[test]
+    fn test_reporter_resolution() {
         let mut context = get_context(alice());
-        // todo remove
-        context.block_timestamp = 100;
-        context.attached_deposit = to_yocto(1000);
+        context.attached_deposit = to_yocto(5000);
         testing_env!(context);
+        let mut contract = init();
 
+        let mut context = get_context(bob());
+        context.attached_deposit = to_yocto(10);
+        testing_env!(context);
         let proposal = ProposalInput {
-            description:  String::from("add carol"),
-            kind: ProposalKind::NewCouncil{ target: carol() },
+            description: String::from("market 0 resolved"),
+            kind: ProposalKind::ResoluteMarket{ market_id: U64(0), payout_numerator: None },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        let index:U64 = contract.add_proposal(proposal);
-        contract.vote(index, Vote::Yes);
+        let id = contract.add_proposal(proposal);
+        let proposal = contract.get_proposal(id);
+        assert_eq!(proposal.bond, U128(to_yocto(10)));
+        assert_eq!(proposal.reporter_reward, Some(U128(to_yocto(1))));
+        assert_eq!(contract.locked_bonds, to_yocto(10));
 
-        // todo verify
-        let mut context = get_context(bob());
+        // not enough votes, the reporter's bond is slashed
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
         testing_env!(context);
-        contract.vote(index, Vote::Yes);
+        contract.execute(id);
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Rejected);
+        assert_eq!(contract.locked_bonds, 0);
+    }
 
+    #[test]
+    #[should_panic(expected = "Only council can create proposals")]
+    fn test_reporter_amend_other_kind() {
         let mut context = get_context(alice());
-        context.block_timestamp = 20000;
+        context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-        contract.finalize(index);
+        let mut contract = init();
+
+        let mut context = get_context(bob());
+        context.attached_deposit = to_yocto(10);
+        testing_env!(context);
+        let proposal = ProposalInput {
+            description: String::from("market 0 resolved"),
+            kind: ProposalKind::ResoluteMarket{ market_id: U64(0), payout_numerator: None },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        let proposal = ProposalInput {
+            description: String::from("pay bob"),
+            kind: ProposalKind::Payout{ target: bob(), amount: U128(to_yocto(1)) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.amend_proposal(id, proposal);
     }
 
-    fn add_dave(contract : &mut FluxDAO) {
+    #[test]
+    fn test_resolute_policy() {
         let mut context = get_context(alice());
-        // todo remove
-        context.block_timestamp = 100;
-        context.attached_deposit = to_yocto(1000);
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+        add_dave(&mut contract);
+        let proposal = ProposalInput {
+            description: String::from("pause protocol"),
+            kind: ProposalKind::ResoluteMarket{
+                market_id: U64(0),
+                payout_numerator: None
+            },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        // vote #1
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        // vote #2
+        let mut context = get_context(bob());
+        testing_env!(context);
+        contract.vote(id, Vote::Yes);
+        // vote #3
+        let mut context = get_context(carol());
+        testing_env!(context);
+        contract.vote(id, Vote::Yes);
+        // verify vote
+        let p:Proposal = contract.get_proposal(id);
+        assert_eq!(p.status, ProposalStatus::Vote);
+        // vote #4
+        let mut context = get_context(dave());
         testing_env!(context);
+        contract.vote(id, Vote::Yes);
+        // finalize
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.finalize_external(id);
+        // verify state
+        let p:Proposal = contract.get_proposal(id);
+        assert_eq!(p.status, ProposalStatus::Success);
+    }
 
+    fn add_passed_resolution(contract: &mut FluxDAO, payout_numerator: Option<Vec<U128>>) -> U64 {
         let proposal = ProposalInput {
-            description:  String::from("add dave"),
-            kind: ProposalKind::NewCouncil{ target: dave() },
+            description: String::from("market 0 resolved"),
+            kind: ProposalKind::ResoluteMarket{ market_id: U64(0), payout_numerator },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        let index:U64 = contract.add_proposal(proposal);
-        contract.vote(index, Vote::Yes);
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let id = contract.add_proposal(proposal);
+        for voter in vec![alice(), bob(), carol(), dave()] {
+            testing_env!(get_context(voter));
+            contract.vote(id, Vote::Yes);
+        }
+        id
+    }
 
-        // todo verify
-        let mut context = get_context(carol());
+    #[test]
+    fn test_dispute_resolution() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-        contract.vote(index, Vote::Yes);
+        let mut contract = init();
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+        add_dave(&mut contract);
+        contract.dispute_window = 100;
+        contract.dispute_bond = to_yocto(1);
+        let id = add_passed_resolution(&mut contract, None);
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Success);
 
-        // let mut context = get_context(bob());
-        // testing_env!(context);
-        // contract.vote(index, Vote::Yes);
+        let mut context = get_context(token());
+        context.attached_deposit = to_yocto(1);
+        context.block_timestamp = 50;
+        testing_env!(context);
+        contract.dispute(id);
+        let proposal = contract.get_proposal(id);
+        assert_eq!(proposal.status, ProposalStatus::Vote);
+        assert_eq!(proposal.vote_yes, 0);
+        assert_eq!(proposal.dispute.unwrap().disputer, token());
+        assert_eq!(contract.locked_bonds, to_yocto(1));
 
+        // the supermajority isn't reached, the disputer wins
         let mut context = get_context(alice());
-        context.block_timestamp = 20000;
+        context.block_timestamp = 50000;
         testing_env!(context);
-        contract.finalize(index);
+        contract.execute(id);
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Rejected);
+        assert_eq!(contract.locked_bonds, 0);
     }
 
-
     #[test]
-    fn test_new() {
+    fn test_dispute_needs_resolute_votes() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-        let mut contract = FluxDAO::new(
-            String::from("do cool shit"),
-            vec![alice(), bob()],
-            U128(1_000_000_u128),
-            U64(1000_u64),
-            U64(2000_u64),
-            protocol_address()
-        );
+        let mut contract = init();
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+        add_dave(&mut contract);
+        contract.dispute_window = 100;
+        contract.dispute_bond = to_yocto(1);
+        let id = add_passed_resolution(&mut contract, None);
 
-        let purpose = String::from("do cool shit");
-        let bond_amount:WrappedBalance  = U128(1_000_000_u128);
-        let vote_period:WrappedDuration = U64(1000_u64);
-        let grace_period:WrappedDuration = U64(2000_u64);
-        let council = vec![alice(), bob()];
-        assert_eq!(contract.get_bond(), bond_amount);
-        assert_eq!(contract.get_vote_period(), vote_period);
-        assert_eq!(contract.get_council(), council);
-        assert_eq!(contract.get_num_proposals(), U64(0));
-        assert_eq!(contract.get_purpose(), purpose);
+        let mut context = get_context(token());
+        context.attached_deposit = to_yocto(1);
+        context.block_timestamp = 50;
+        testing_env!(context);
+        contract.dispute(id);
 
-        assert_eq!(contract.purpose, purpose);
-        assert_eq!(contract.bond, bond_amount.into());
-        //assert_eq!(contract.vote_period, vote_period.into());
-        //assert_eq!(contract.grace_period, grace_period.into());
-        assert_eq!(contract.policy.max_amount, U128(0));
-        assert_eq!(contract.council.len(), 2);
-        assert_eq!(contract.proposals.len(), 0);
+        // 3 of 4 is a 2/3 supermajority but less than the 4 votes the resolution needed
+        for voter in vec![alice(), bob(), carol()] {
+            let mut context = get_context(voter);
+            context.block_timestamp = 50;
+            testing_env!(context);
+            contract.vote(id, Vote::Yes);
+        }
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Vote);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.execute(id);
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Rejected);
     }
 
     #[test]
-    #[should_panic(expected = "Not enough deposit")]
-    fn test_add_new_council_proposal_insufficient_deposit() {
+    #[should_panic(expected = "Grace period active")]
+    fn test_resolution_in_dispute_window() {
         let mut context = get_context(alice());
-        context.attached_deposit = to_yocto(2000);
+        context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
-        let mut contract = FluxDAO::new(
-            String::from("do cool shit"),
-            vec![alice()],
-            U128(to_yocto(2)),
-            U64(0),
-            U64(0),
-            protocol_address()
-        );
-        let proposal = ProposalInput {
-            description: String::from("carol is cool"),
-            kind: ProposalKind::NewCouncil{target: carol() },
-        };
+        let mut contract = init();
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+        add_dave(&mut contract);
+        contract.dispute_window = 100;
+        let id = add_passed_resolution(&mut contract, None);
+
         let mut context = get_context(alice());
-        context.attached_deposit = to_yocto(1);
+        context.block_timestamp = 50;
         testing_env!(context);
-        contract.add_proposal(proposal);
+        contract.execute(id);
     }
 
     #[test]
-    #[should_panic(expected = "Description length is too long")]
-    fn test_add_new_council_invalid_description() {
+    #[should_panic(expected = "ERR_RESOLUTION_IN_PROGRESS")]
+    fn test_conflicting_resolutions() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
         let mut contract = init();
-        let proposal = ProposalInput {
-            description: String::from("a").repeat(281),
-            kind: ProposalKind::NewCouncil { target: carol() },
-        };
-        contract.add_proposal(proposal);
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+        add_dave(&mut contract);
+        let first = add_passed_resolution(&mut contract, Some(vec![U128(1), U128(0)]));
+        let second = add_passed_resolution(&mut contract, Some(vec![U128(0), U128(1)]));
+        assert_eq!(contract.get_resolution_proposals(U64(0)), vec![first, second]);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.execute(first);
+        contract.execute(second);
     }
 
     #[test]
-    fn test_add_new_council_proposal() {
+    fn test_resolution_of_resolved_market() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
         let mut contract = init();
-        let description = String::from("carol is cool");
-        let proposal = ProposalInput {
-            description: description.clone(),
-            kind: ProposalKind::NewCouncil{ target: carol() },
-        };
-
-        // Carol (not in council) creates a proposal to include her in the counsil
-        let index:U64 = contract.add_proposal(proposal);
-        // TODO, verify contract balance in NEAR
-        assert_eq!(index, U64(0));
-        assert_eq!(contract.get_num_proposals(), U64(1));
-        let mut proposal = contract.get_proposal(U64(0));
-        assert_eq!(proposal.status, ProposalStatus::Vote);
-        assert_eq!(proposal.proposer, alice());
-        //assert_eq!(proposal.kind.target, carol());
-        assert_eq!(proposal.description, description);
-        //assert_eq!(proposal.kind, ProposalKind::NewCouncil);
-        // TODO, how to get block timestamp of tx
-        //assert_eq!(proposal.vote_period_end, timestamp+U64(1000_u64));
-        assert_eq!(proposal.vote_yes, 0);
-        assert_eq!(proposal.vote_no, 0);
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+        add_dave(&mut contract);
+        let first = add_passed_resolution(&mut contract, Some(vec![U128(1), U128(0)]));
 
-        context = get_context(alice());
+        let mut context = get_context(token());
+        context.attached_deposit = to_yocto(10);
         testing_env!(context);
+        let second = contract.add_proposal(ProposalInput {
+            description: String::from("market 0 resolved"),
+            kind: ProposalKind::ResoluteMarket{ market_id: U64(0), payout_numerator: Some(vec![U128(0), U128(1)]) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        });
+        for voter in vec![alice(), bob(), carol(), dave()] {
+            testing_env!(get_context(voter));
+            contract.vote(second, Vote::Yes);
+        }
+        let mut proposal = contract.proposals.get(first.into()).unwrap();
+        proposal.status = ProposalStatus::Finalized;
+        contract.proposals.replace(first.into(), &proposal);
+        contract.resolving_markets.insert(&0, &first.into());
 
-        assert_eq!(contract.council.len(), 1);
-        contract.vote(U64(0), Vote::Yes);
-
-        poll_finalize(&mut contract, U64(0));
-        proposal = contract.get_proposal(U64(0));
-
-        assert_eq!(proposal.vote_yes, 1);
-        assert_eq!(proposal.vote_no, 0);
-        assert_eq!(proposal.status, ProposalStatus::Finalized);
-        assert_eq!(proposal.proposer, alice());
-       // assert_eq!(proposal.kind.target, carol());
-        assert_eq!(proposal.description, description);
-        assert_eq!(contract.council.len(), 2);
-    }
-
-    #[test]
-    fn test_exit_dao() {
+        // the reporter isn't at fault, the bond is refunded instead of slashed
         let mut context = get_context(alice());
-        context.attached_deposit = to_yocto(5000);
+        context.block_timestamp = 50000;
         testing_env!(context);
-
-        let mut contract = init();
-        assert_eq!(contract.council.len(), 1);
-        contract.exit_dao();
-        assert_eq!(contract.council.len(), 0);
-        // TODO test for running polls
+        match contract.execute(second) {
+            PromiseOrValue::Promise(_) => {}
+            PromiseOrValue::Value(_) => panic!("bond wasn't refunded"),
+        }
+        assert_eq!(contract.get_proposal(second).status, ProposalStatus::Rejected);
+        assert_eq!(contract.locked_bonds, 0);
     }
 
     #[test]
-    #[should_panic(expected = "Only council can create proposals")]
-    fn test_proposal_outside_council() {
+    #[should_panic(expected = "ERR_DUPLICATE_RESOLUTION")]
+    fn test_duplicate_resolution() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
         let mut contract = init();
-        let mut context = get_context(bob());
-        context.attached_deposit = to_yocto(5000);
-        testing_env!(context);
-
-        let description = String::from("bob sucks");
-        let proposal = ProposalInput {
-            description: description.clone(),
-            kind: ProposalKind::NewCouncil{target: bob() },
-        };
-        let index:U64 = contract.add_proposal(proposal);
-        assert_eq!(index, U64(0));
+        for _ in 0..2 {
+            let proposal = ProposalInput {
+                description: String::from("market 0 resolved"),
+                kind: ProposalKind::ResoluteMarket{ market_id: U64(0), payout_numerator: None },
+                depends_on: vec![],
+                link: None,
+                content_hash: None,
+            };
+            contract.add_proposal(proposal);
+        }
     }
 
     #[test]
-    fn test_remove_council_proposal() {
+    fn test_resolute_policy_fail() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
         let mut contract = init();
         add_bob(&mut contract);
         add_carol(&mut contract);
-        let description = String::from("bob sucks");
+        add_dave(&mut contract);
         let proposal = ProposalInput {
-            description: description.clone(),
-            kind: ProposalKind::RemoveCouncil{target: bob()},
+            description: String::from("pause protocol"),
+            kind: ProposalKind::ResoluteMarket{
+                market_id: U64(0),
+                payout_numerator: None
+            },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        let index:U64 = contract.add_proposal(proposal);
-
-        assert_eq!(contract.council.len(), 3);
-
-        let mut context = get_context(alice());
+        // vote #1
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        // vote #2
+        let mut context = get_context(bob());
         testing_env!(context);
-        contract.vote(index, Vote::Yes);
-
+        contract.vote(id, Vote::Yes);
+        // vote #3
         let mut context = get_context(carol());
-
         testing_env!(context);
-        contract.vote(index, Vote::Yes);
-        context = get_context(alice());
-        // TODO, is sending near expected in this case
-        // this amount pays out the exit bond
-        context.attached_deposit = to_yocto(5000);
+        contract.vote(id, Vote::Yes);
+        // verify vote
+        let p:Proposal = contract.get_proposal(id);
+        assert_eq!(p.status, ProposalStatus::Vote);
+        // finalize
+        let mut context = get_context(alice());
         context.block_timestamp = 50000;
         testing_env!(context);
-        contract.finalize(index);
+        contract.finalize_external(id);
+        // verify state
+        let p:Proposal = contract.get_proposal(id);
+        assert_eq!(p.status, ProposalStatus::Rejected);
+    }
+
+    fn add_unvoted_by_bob(contract : &mut FluxDAO, count: u64) {
+        for _ in 0..count {
+            let proposal = ProposalInput {
+                description: String::from("x"),
+                kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+                depends_on: vec![],
+                link: None,
+                content_hash: None,
+            };
+            let id = contract.add_proposal(proposal);
+            contract.vote(id, Vote::Yes);
+        }
+    }
+
+    fn add_staked_bob(contract : &mut FluxDAO) {
+        contract.council_stake = 100;
+        contract.stake_cooldown = 1000;
+        add_bob(contract);
+        assert_eq!(contract.council.len(), 1);
+        assert_eq!(contract.get_invitation(bob()), Some(U128(100)));
 
+        let mut context = get_context(bob());
+        context.attached_deposit = 100;
+        testing_env!(context);
+        contract.accept_council_seat();
         assert_eq!(contract.council.len(), 2);
+        assert_eq!(contract.get_member_stake(bob()), U128(100));
+        assert_eq!(contract.get_invitation(bob()), None);
     }
 
     #[test]
-    fn test_remove_council_proposal_voteself() {
+    fn test_council_stake_slash_and_withdraw() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
         let mut contract = init();
-        assert_eq!(contract.council.len(), 1);
-        add_bob(&mut contract);
-        assert_eq!(contract.council.len(), 2);
-        add_carol(&mut contract);
-        let description = String::from("bob sucks");
+        add_staked_bob(&mut contract);
+
+        let mut context = get_context(alice());
+        testing_env!(context);
         let proposal = ProposalInput {
-            description: description.clone(),
-            kind: ProposalKind::RemoveCouncil{target:bob()},
+            description: String::from("slash bob"),
+            kind: ProposalKind::SlashMember{ target: bob(), amount: U128(30) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        let index:U64 = contract.add_proposal(proposal);
-        assert_eq!(contract.council.len(), 3);
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        let mut context = get_context(bob());
+        testing_env!(context);
+        contract.vote(id, Vote::Yes);
+        poll_finalize(&mut contract, id);
+        assert_eq!(contract.get_member_stake(bob()), U128(70));
 
-        let mut context = get_context(alice());
+        let mut context = get_context(bob());
+        context.block_timestamp = 50000;
         testing_env!(context);
-        contract.vote(index, Vote::Yes);
+        contract.exit_dao();
+        let pending = contract.get_pending_stake(bob()).unwrap();
+        assert_eq!(pending.amount, U128(70));
+        assert_eq!(pending.available_at, U64(51000));
 
         let mut context = get_context(bob());
+        context.block_timestamp = 51000;
         testing_env!(context);
-        contract.vote(index, Vote::Yes);
+        contract.withdraw_stake();
+        assert!(contract.get_pending_stake(bob()).is_none());
+    }
 
-        context = get_context(alice());
-        // todo check deposit
+    #[test]
+    #[should_panic(expected = "ERR_STAKE_COOLDOWN_ACTIVE")]
+    fn test_council_stake_withdraw_cooldown() {
+        let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
-        context.block_timestamp = 50000;
         testing_env!(context);
-        contract.finalize(index);
+        let mut contract = init();
+        add_staked_bob(&mut contract);
 
-        assert_eq!(contract.council.len(), 2);
+        let mut context = get_context(bob());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.exit_dao();
+        contract.withdraw_stake();
     }
 
     #[test]
-    fn test_payout_proposal() {
+    #[should_panic(expected = "Not enough deposit")]
+    fn test_accept_council_seat_insufficient_stake() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
         let mut contract = init();
-        let description = String::from("bob payout");
+        contract.council_stake = 100;
+        add_bob(&mut contract);
+
+        let mut context = get_context(bob());
+        context.attached_deposit = 99;
+        testing_env!(context);
+        contract.accept_council_seat();
+    }
+
+    fn set_token_weighted(contract : &mut FluxDAO) {
+        contract.staking_token = Some(token());
         let proposal = ProposalInput {
-            description: description.clone(),
-            kind: ProposalKind::Payout{ target: bob(), amount: U128(to_yocto(1)) },
+            description: String::from("token voting"),
+            kind: ProposalKind::ChangeVotingMode{ mode: VotingMode::TokenWeighted { token_id: token() } },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        contract.add_proposal(proposal);
-        contract.vote(U64(0), Vote::Yes);
-        // TODO, check balance
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        poll_finalize(contract, id);
+        assert_eq!(contract.get_voting_mode(), VotingMode::TokenWeighted { token_id: token() });
     }
 
     #[test]
-    fn test_vote_period_proposal() {
+    fn test_token_weighted_vote_status() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
         let mut contract = init();
-        let description = String::from("vote period");
+        set_token_weighted(&mut contract);
+
         let proposal = ProposalInput {
-            description: description.clone(),
-            kind: ProposalKind::ChangeVotePeriod{ vote_period: U64(1) },
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        contract.add_proposal(proposal);
-        assert_eq!(contract.get_vote_period(), U64(10));
-        contract.vote(U64(0), Vote::Yes);
+        let id = contract.add_proposal(proposal);
+        let mut p = contract.get_proposal(id);
+        assert_eq!(p.voting_mode, VotingMode::TokenWeighted { token_id: token() });
 
-        poll_finalize(&mut contract, U64(0));
-        assert_eq!(contract.get_vote_period(), U64(1));
-        // TODO, check balance
+        // no snapshot of the total supply yet
+        p.weight_yes = U128(100);
+        assert_eq!(p.weighted_vote_status(&contract.policy), ProposalStatus::Vote);
+
+        p.total_weight = U128(200);
+        assert_eq!(p.weighted_vote_status(&contract.policy), ProposalStatus::Vote);
+        p.weight_yes = U128(101);
+        assert_eq!(p.weighted_vote_status(&contract.policy), ProposalStatus::Success);
     }
 
     #[test]
-    fn test_change_bond_proposal() {
+    #[should_panic(expected = "Proposal is token weighted")]
+    fn test_token_weighted_council_vote() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
+        let mut contract = init();
+        set_token_weighted(&mut contract);
+
+        let proposal = ProposalInput {
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+    }
 
+    #[test]
+    fn test_token_weighted_resolute_market_by_council() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
         let mut contract = init();
-        let description = String::from("bond");
+        set_token_weighted(&mut contract);
+
         let proposal = ProposalInput {
-            description: description.clone(),
-            kind: ProposalKind::ChangeBond{ bond: U128(1) },
+            description: String::from("resolute"),
+            kind: ProposalKind::ResoluteMarket{ market_id: U64(0), payout_numerator: None },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        contract.add_proposal(proposal);
-        assert_eq!(contract.get_bond(), U128(0));
-        contract.vote(U64(0), Vote::Yes);
+        let id = contract.add_proposal(proposal);
+        assert_eq!(contract.get_proposal(id).voting_mode, VotingMode::Council);
+        contract.vote(id, Vote::Yes);
+    }
 
-        poll_finalize(&mut contract, U64(0));
-        assert_eq!(contract.get_bond(), U128(1));
-        // TODO, check balance
+    fn stake(contract : &mut FluxDAO, account_id: AccountId, amount: u128) {
+        let mut context = get_context(token());
+        context.block_timestamp = 40000;
+        testing_env!(context);
+        contract.ft_on_transfer(account_id, U128(amount), String::new());
     }
 
     #[test]
-    fn test_change_policy_proposal() {
+    fn test_staked_vote_weighted() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
         let mut contract = init();
-        let description = String::from("policy");
-        let policy = PolicyItem {
-            max_amount: 100.into(),
-            votes: NumOrRatio::Ratio(1, 2),
-        };
+        set_token_weighted(&mut contract);
+        stake(&mut contract, bob(), 60);
+        stake(&mut contract, carol(), 40);
+        assert_eq!(contract.get_total_staked(), U128(100));
+        assert_eq!(contract.get_staked_balance(bob()), U128(60));
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
         let proposal = ProposalInput {
-            description: description.clone(),
-            kind: ProposalKind::ChangePolicy{ policy },
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        contract.add_proposal(proposal);
-        assert_eq!(contract.policy.max_amount, U128(0));
-        contract.vote(U64(0), Vote::Yes);
+        let id = contract.add_proposal(proposal);
+        assert_eq!(contract.get_proposal(id).total_weight, U128(100));
+
+        let mut context = get_context(carol());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.vote_weighted(id, Vote::Yes);
+        assert_eq!(contract.get_proposal(id).status, ProposalStatus::Vote);
 
-        poll_finalize(&mut contract, U64(0));
-        assert_eq!(contract.policy.max_amount, U128(100));
+        let mut context = get_context(bob());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.vote_weighted(id, Vote::Yes);
+        let p = contract.get_proposal(id);
+        assert_eq!(p.weight_yes, U128(100));
+        assert_eq!(p.status, ProposalStatus::Success);
     }
 
     #[test]
-    fn test_change_purpose_proposal() {
+    #[should_panic(expected = "ERR_NOT_STAKING_TOKEN")]
+    fn test_token_weighted_without_staking_token() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
-        let purpose = String::from("do cool shit");
         let mut contract = init();
-        let description = String::from("do cooler shit");
         let proposal = ProposalInput {
-            description: description.clone(),
-            kind: ProposalKind::ChangePurpose{ purpose: description.clone() },
+            description: String::from("token voting"),
+            kind: ProposalKind::ChangeVotingMode{ mode: VotingMode::TokenWeighted { token_id: token() } },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
         contract.add_proposal(proposal);
-        assert_eq!(contract.purpose, purpose);
-        contract.vote(U64(0), Vote::Yes);
-
-        poll_finalize(&mut contract, U64(0));
-        assert_eq!(contract.purpose, description);
     }
 
     #[test]
-    fn test_change_bond_proposal_fail() {
+    fn test_stake_after_proposal_not_counted() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
         let mut contract = init();
-        let description = String::from("bond");
+        set_token_weighted(&mut contract);
+        stake(&mut contract, bob(), 60);
+        stake(&mut contract, carol(), 40);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
         let proposal = ProposalInput {
-            description: description.clone(),
-            kind: ProposalKind::ChangeBond{ bond: U128(1) },
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        let index:U64 = contract.add_proposal(proposal);
+        let id = contract.add_proposal(proposal);
 
-        assert_eq!(contract.get_bond(), U128(0));
-        contract.vote(index, Vote::No);
+        let mut context = get_context(token());
+        context.block_timestamp = 50001;
+        testing_env!(context);
+        contract.ft_on_transfer(carol(), U128(1000), String::new());
 
-        poll_finalize(&mut contract, index);
-        let p:Proposal = contract.get_proposal(index);
-        assert_eq!(p.status, ProposalStatus::Rejected);
-        assert_eq!(contract.get_bond(), U128(0));
-        // TODO, check balance
+        let mut context = get_context(carol());
+        context.block_timestamp = 50001;
+        testing_env!(context);
+        contract.vote_weighted(id, Vote::Yes);
+        let p = contract.get_proposal(id);
+        assert_eq!(p.weight_yes, U128(40));
+        assert_eq!(p.status, ProposalStatus::Vote);
     }
 
     #[test]
-    #[should_panic(expected = "Only council can vote")]
-    fn test_no_council_vote() {
+    #[should_panic(expected = "ERR_STAKE_LOCKED_BY_VOTE")]
+    fn test_unstake_locked_by_vote() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
         let mut contract = init();
+        set_token_weighted(&mut contract);
+        stake(&mut contract, bob(), 60);
+
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
         let proposal = ProposalInput {
             description: String::from("x"),
-            kind: ProposalKind::ChangePurpose{ purpose:String::from("y") },
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        contract.add_proposal(proposal);
+        let id = contract.add_proposal(proposal);
 
         let mut context = get_context(bob());
+        context.block_timestamp = 50000;
         testing_env!(context);
-        contract.vote(U64(0), Vote::Yes);
+        contract.vote_weighted(id, Vote::No);
+        contract.unstake(U128(60));
     }
 
     #[test]
-    #[should_panic(expected = "No proposal with such id")]
-    fn test_no_proposal_vote() {
+    fn test_unstake_and_withdraw() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
         let mut contract = init();
-        contract.vote(U64(0), Vote::Yes);
+        contract.staking_token = Some(token());
+        contract.unbonding_period = 100;
+        stake(&mut contract, bob(), 60);
+
+        let mut context = get_context(bob());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.unstake(U128(20));
+        assert_eq!(contract.get_staked_balance(bob()), U128(40));
+        assert_eq!(contract.get_total_staked(), U128(40));
+        let unbonding = contract.get_unbonding(bob()).unwrap();
+        assert_eq!(unbonding.amount, U128(20));
+        assert_eq!(unbonding.available_at, U64(50100));
+
+        let mut context = get_context(bob());
+        context.block_timestamp = 50100;
+        testing_env!(context);
+        contract.withdraw_unstaked();
+        assert!(contract.get_unbonding(bob()).is_none());
     }
 
     #[test]
-    #[should_panic(expected = "Proposal not active voting")]
-    fn test_proposal_already_finalized() {
+    fn test_ft_deposit_to_treasury() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
         let mut contract = init();
-        let proposal = ProposalInput {
-            description: String::from("x"),
-            kind: ProposalKind::ChangePurpose{ purpose:String::from("y") },
-        };
-        contract.add_proposal(proposal);
-        contract.vote(U64(0), Vote::Yes);
+        contract.staking_token = Some(token());
+        // any other token goes to the treasury
+        contract.ft_on_transfer(bob(), U128(10), String::new());
+        assert_eq!(contract.get_ft_balance(alice()), U128(10));
+        assert_eq!(contract.get_staked_balance(bob()), U128(0));
 
-        poll_finalize(&mut contract, U64(0));
-        contract.vote(U64(0), Vote::Yes);
+        stake(&mut contract, bob(), 5);
+        let mut context = get_context(token());
+        testing_env!(context);
+        contract.ft_on_transfer(bob(), U128(7), String::from("deposit"));
+        assert_eq!(contract.get_staked_balance(bob()), U128(5));
+        assert_eq!(contract.get_ft_balances(), vec![(alice(), U128(10)), (token(), U128(7))]);
     }
 
     #[test]
-    #[should_panic(expected = "Already voted")]
-    fn test_already_voted() {
+    fn test_ft_payout() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
         let mut contract = init();
-        add_bob(&mut contract);
+        let mut context = get_context(token());
+        testing_env!(context);
+        contract.ft_on_transfer(bob(), U128(10), String::new());
+
+        let mut context = get_context(alice());
+        testing_env!(context);
         let proposal = ProposalInput {
-            description: String::from("x"),
-            kind: ProposalKind::ChangePurpose{ purpose:String::from("y") },
+            description: String::from("pay bob"),
+            kind: ProposalKind::FtPayout{ token_id: token(), target: bob(), amount: U128(4) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        contract.add_proposal(proposal);
-        contract.vote(U64(1), Vote::Yes);
-        contract.vote(U64(1), Vote::Yes);
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.finalize_external(id);
+        assert_eq!(contract.get_ft_balance(token()), U128(6));
     }
 
     #[test]
-    fn test_change_protocol_address() {
+    #[should_panic(expected = "ERR_EXECUTION_IN_PROGRESS")]
+    fn test_ft_payout_executed_twice() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
-        let protocol_new : AccountId = "protocol2".to_string();
         let mut contract = init();
-        assert_eq!(contract.protocol_address, protocol_address());
+        let mut context = get_context(token());
+        testing_env!(context);
+        contract.ft_on_transfer(bob(), U128(10), String::new());
+
+        let mut context = get_context(alice());
+        testing_env!(context);
         let proposal = ProposalInput {
-            description: String::from("change protocol address"),
-            kind: ProposalKind::ChangeProtocolAddress{ address: protocol_new.clone() }
+            description: String::from("pay bob"),
+            kind: ProposalKind::FtPayout{ token_id: token(), target: bob(), amount: U128(4) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        contract.add_proposal(proposal);
-        contract.vote(U64(0), Vote::Yes);
-        poll_finalize(&mut contract, U64(0));
-        assert_eq!(contract.protocol_address, protocol_new.clone());
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
+        let mut context = get_context(alice());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        contract.execute(id);
+        assert!(contract.get_proposal(id).executing);
+        // the transfer hasn't returned yet
+        contract.execute(id);
     }
 
     #[test]
-    #[should_panic(expected = "Grace period active")]
-    fn test_grace_period_active() {
+    #[should_panic(expected = "ERR_INSUFFICIENT_TREASURY_BALANCE")]
+    fn test_ft_payout_insufficient_balance() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-
-        let protocol_new : AccountId = "protocol".to_string();
         let mut contract = init();
-        assert_eq!(contract.protocol_address, protocol_address());
         let proposal = ProposalInput {
-            description: String::from("change protocol address"),
-            kind: ProposalKind::ChangeProtocolAddress{ address: protocol_new.clone() }
+            description: String::from("pay bob"),
+            kind: ProposalKind::FtPayout{ token_id: token(), target: bob(), amount: U128(4) },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        contract.add_proposal(proposal);
-        contract.vote(U64(0), Vote::Yes);
-
+        let id = contract.add_proposal(proposal);
+        contract.vote(id, Vote::Yes);
         let mut context = get_context(alice());
-        context.block_timestamp = 5;
+        context.block_timestamp = 50000;
         testing_env!(context);
-        contract.finalize(U64(0));
+        contract.finalize_external(id);
     }
 
     #[test]
-    fn test_pause_protocol() {
+    #[should_panic(expected = "ERR_UNKNOWN_PROPOSAL_KIND")]
+    fn test_delegate_unknown_kind() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
         let mut contract = init();
-        assert_eq!(contract.protocol_address, protocol_address());
-        let proposal = ProposalInput {
-            description: String::from("pause protocol"),
-            kind: ProposalKind::PauseProtocol{ }
-        };
-        let id = contract.add_proposal(proposal);
-        contract.vote(U64(0), Vote::Yes);
-        // dont chagnge block number after voting (e.g. no grace period)
-        contract.finalize_external(id);
+        add_bob(&mut contract);
+
+        let mut context = get_context(bob());
+        testing_env!(context);
+        contract.delegate(alice(), Some(vec![String::from("ChangePurpse")]));
     }
 
     #[test]
-    fn test_unpause_protocol() {
+    fn test_delegated_vote() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
         let mut contract = init();
-        assert_eq!(contract.protocol_address, protocol_address());
+        add_bob(&mut contract);
+        add_carol(&mut contract);
+
+        let mut context = get_context(bob());
+        testing_env!(context);
+        contract.delegate(alice(), Some(vec![String::from("ChangePurpose")]));
+        assert_eq!(contract.get_voting_power(alice(), String::from("ChangePurpose")), U64(2));
+        assert_eq!(contract.get_voting_power(bob(), String::from("ChangePurpose")), U64(0));
+        assert_eq!(contract.get_voting_power(alice(), String::from("ChangeBond")), U64(1));
+        assert_eq!(contract.get_voting_power(bob(), String::from("ChangeBond")), U64(1));
+
+        let mut context = get_context(alice());
+        testing_env!(context);
         let proposal = ProposalInput {
-            description: String::from("pause protocol"),
-            kind: ProposalKind::UnpauseProtocol{ }
+            description: String::from("x"),
+            kind: ProposalKind::ChangePurpose{ purpose: String::from("y") },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
         let id = contract.add_proposal(proposal);
-        contract.vote(U64(0), Vote::Yes);
-        // dont chagnge block number after voting (e.g. no grace period)
-        contract.finalize_external(id);
+        contract.vote(id, Vote::Yes);
+        let p = contract.get_proposal(id);
+        assert_eq!(p.vote_yes, 2);
+        assert_eq!(p.votes.get(&bob()), Some(&Vote::Yes));
+        assert_eq!(p.status, ProposalStatus::Success);
     }
 
     #[test]
-    fn test_resolute_policy() {
+    #[should_panic(expected = "ERR_DELEGATION_CYCLE")]
+    fn test_delegation_cycle() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
         let mut contract = init();
         add_bob(&mut contract);
         add_carol(&mut contract);
-        add_dave(&mut contract);
-        let proposal = ProposalInput {
-            description: String::from("pause protocol"),
-            kind: ProposalKind::ResoluteMarket{
-                market_id: U64(0),
-                payout_numerator: None
-            }
-        };
-        // vote #1
-        let id = contract.add_proposal(proposal);
-        contract.vote(id, Vote::Yes);
-        // vote #2
+
+        let mut context = get_context(alice());
+        testing_env!(context);
+        contract.delegate(bob(), None);
         let mut context = get_context(bob());
         testing_env!(context);
-        contract.vote(id, Vote::Yes);
-        // vote #3
+        contract.delegate(carol(), Some(vec![String::from("ResoluteMarket")]));
         let mut context = get_context(carol());
         testing_env!(context);
-        contract.vote(id, Vote::Yes);
-        // verify vote
-        let p:Proposal = contract.get_proposal(id);
-        assert_eq!(p.status, ProposalStatus::Vote);
-        // vote #4
-        let mut context = get_context(dave());
+        contract.delegate(alice(), None);
+    }
+
+    #[test]
+    fn test_participation() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-        contract.vote(id, Vote::Yes);
-        // finalize
+        let mut contract = init();
+        add_bob(&mut contract);
+        add_unvoted_by_bob(&mut contract, 2);
+
         let mut context = get_context(alice());
         context.block_timestamp = 50000;
         testing_env!(context);
-        contract.finalize_external(id);
-        // verify state
-        let p:Proposal = contract.get_proposal(id);
-        assert_eq!(p.status, ProposalStatus::Success);
+        let alice_participation = contract.get_participation(alice(), U64(10));
+        assert_eq!(alice_participation.voted, U64(3));
+        assert_eq!(alice_participation.total, U64(3));
+        // proposal adding bob was created before he joined
+        let bob_participation = contract.get_participation(bob(), U64(10));
+        assert_eq!(bob_participation.voted, U64(0));
+        assert_eq!(bob_participation.total, U64(2));
     }
 
     #[test]
-    fn test_resolute_policy_fail() {
+    fn test_propose_inactive_removal() {
         let mut context = get_context(alice());
         context.attached_deposit = to_yocto(5000);
         testing_env!(context);
         let mut contract = init();
+        contract.inactivity_policy.window = U64(2);
         add_bob(&mut contract);
-        add_carol(&mut contract);
-        add_dave(&mut contract);
+        add_unvoted_by_bob(&mut contract, 2);
+
+        let mut context = get_context(carol());
+        context.block_timestamp = 50000;
+        testing_env!(context);
+        let id = contract.propose_inactive_removal(bob());
+        let p:Proposal = contract.get_proposal(id);
+        assert_eq!(p.proposer, carol());
+        match p.kind {
+            ProposalKind::RemoveCouncil { target } => assert_eq!(target, bob()),
+            _ => panic!("wrong proposal kind"),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_PARTICIPATION_RATIO")]
+    fn test_invalid_inactivity_policy() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
         let proposal = ProposalInput {
-            description: String::from("pause protocol"),
-            kind: ProposalKind::ResoluteMarket{
-                market_id: U64(0),
-                payout_numerator: None
-            }
+            description: String::from("x"),
+            kind: ProposalKind::ChangeInactivityPolicy{ policy: InactivityPolicy {
+                window: U64(10),
+                min_participation: ParticipationRatio { numerator: U64(1), denominator: U64(0) },
+            } },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
         };
-        // vote #1
-        let id = contract.add_proposal(proposal);
-        contract.vote(id, Vote::Yes);
-        // vote #2
-        let mut context = get_context(bob());
+        contract.add_proposal(proposal);
+    }
+
+    #[test]
+    #[should_panic(expected = "Member is not inactive")]
+    fn test_propose_inactive_removal_active_member() {
+        let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
         testing_env!(context);
-        contract.vote(id, Vote::Yes);
-        // vote #3
+        let mut contract = init();
+        contract.inactivity_policy.window = U64(2);
+        add_bob(&mut contract);
+        add_unvoted_by_bob(&mut contract, 2);
+
         let mut context = get_context(carol());
+        context.block_timestamp = 50000;
         testing_env!(context);
-        contract.vote(id, Vote::Yes);
-        // verify vote
-        let p:Proposal = contract.get_proposal(id);
-        assert_eq!(p.status, ProposalStatus::Vote);
-        // finalize
+        contract.propose_inactive_removal(alice());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only council can amend proposals")]
+    fn test_amend_inactive_removal_into_resolution() {
         let mut context = get_context(alice());
+        context.attached_deposit = to_yocto(5000);
+        testing_env!(context);
+        let mut contract = init();
+        contract.inactivity_policy.window = U64(2);
+        add_bob(&mut contract);
+        add_unvoted_by_bob(&mut contract, 2);
+
+        let mut context = get_context(carol());
         context.block_timestamp = 50000;
+        context.attached_deposit = to_yocto(10);
         testing_env!(context);
-        contract.finalize_external(id);
-        // verify state
-        let p:Proposal = contract.get_proposal(id);
-        assert_eq!(p.status, ProposalStatus::Rejected);
+        let id = contract.propose_inactive_removal(bob());
+        let proposal = ProposalInput {
+            description: String::from("market 0 resolved"),
+            kind: ProposalKind::ResoluteMarket{ market_id: U64(0), payout_numerator: None },
+            depends_on: vec![],
+            link: None,
+            content_hash: None,
+        };
+        contract.amend_proposal(id, proposal);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,30 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U64;
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::types::{ WrappedBalance, WrappedTimestamp };
+
+/// Stake waiting for a cooldown or unbonding period to pass before it can be withdrawn.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingStake {
+    pub amount: WrappedBalance,
+    /// Timestamp after which the stake can be withdrawn.
+    pub available_at: WrappedTimestamp,
+}
+
+/// Minimum and maximum number of council members.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CouncilBounds {
+    pub min_council: U64,
+    pub max_council: U64,
+}
+
+/// Staked governance tokens of an account from `timestamp` until the next checkpoint.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakeCheckpoint {
+    pub timestamp: WrappedTimestamp,
+    pub amount: WrappedBalance,
+}
@@ -1,5 +1,6 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::Balance;
 
 use crate::types::{ WrappedBalance, NumOrRatio };
 
@@ -18,4 +19,20 @@ impl PolicyItem {
             NumOrRatio::Ratio(l, r) => std::cmp::min(num_council * l / r + 1, num_council),
         }
     }
+
+    /// Vote weight required to approve, given the total weight that can be voted with.
+    pub fn weight_threshold(&self, total_weight: Balance) -> Balance {
+        match self.votes {
+            NumOrRatio::Number(weight) => weight as Balance,
+            NumOrRatio::Ratio(l, r) => std::cmp::min(total_weight * l as Balance / r as Balance + 1, total_weight),
+        }
+    }
+
+    /// Number of votes required regardless of council size, if any.
+    pub fn fixed_votes(&self) -> Option<u64> {
+        match self.votes {
+            NumOrRatio::Number(num_votes) => Some(num_votes),
+            NumOrRatio::Ratio(_, _) => None,
+        }
+    }
 }
\ No newline at end of file
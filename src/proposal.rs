@@ -4,18 +4,29 @@ use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{ AccountId, Balance, env };
 use near_sdk::{ json_types::{U64, U128} };
-use crate::types::{ WrappedBalance, WrappedDuration, Duration, Vote };
+use crate::types::{ WrappedBalance, WrappedDuration, WrappedTimestamp, Duration, Vote, VotingMode };
 use crate::policy_item::{ PolicyItem };
+use crate::inactivity::{ InactivityPolicy };
 use crate::proposal_status::{ ProposalStatus };
+use crate::dispute::{ Dispute };
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct ProposalInput {
     pub description: String,
     pub kind: ProposalKind,
+    /// Proposals that must be finalized before this one can be executed.
+    #[serde(default)]
+    pub depends_on: Vec<U64>,
+    /// Where the full rationale of the proposal can be read.
+    #[serde(default)]
+    pub link: Option<String>,
+    /// Hex encoded sha256 of the document at `link`.
+    #[serde(default)]
+    pub content_hash: Option<String>,
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 #[serde(tag = "type")]
 pub enum ProposalKind {
@@ -32,7 +43,132 @@ pub enum ProposalKind {
     AddTokenWhitelist { to_add: AccountId },
     SetGov { new_gov: AccountId },
     PauseProtocol {},
-    UnpauseProtocol {}
+    UnpauseProtocol {},
+    ChangeInactivityPolicy { policy: InactivityPolicy },
+    ChangeCouncilBounds { min_council: U64, max_council: U64 },
+    ChangeCouncilStake { stake: WrappedBalance, cooldown: WrappedDuration },
+    SlashMember { target: AccountId, amount: WrappedBalance },
+    ChangeVotingMode { mode: VotingMode },
+    ChangeStaking { token_id: AccountId, unbonding_period: WrappedDuration },
+    FtPayout { token_id: AccountId, target: AccountId, amount: WrappedBalance },
+    CreateStream {
+        recipient: AccountId,
+        token: Option<AccountId>,
+        total: WrappedBalance,
+        start: WrappedTimestamp,
+        end: WrappedTimestamp,
+        cliff: WrappedTimestamp
+    },
+    CancelStream { id: U64 },
+    GrantBudget {
+        account_id: AccountId,
+        token: Option<AccountId>,
+        cap: WrappedBalance,
+        period: WrappedDuration
+    },
+    RevokeBudget { account_id: AccountId },
+    SetTimelock { kind: String, delay: WrappedDuration },
+    SetGuardians { guardians: Vec<AccountId> },
+    SetKindGracePeriod { kind: String, grace_period: Option<WrappedDuration> },
+    ChangeExecutionWindow { execution_window: WrappedDuration, refund_expired_bonds: bool },
+    SetSecretVoting { kind: String, reveal_period: Option<WrappedDuration> },
+    ChangeReporterBond { bond: WrappedBalance, reward: WrappedBalance },
+    ChangeDisputeSettings { window: WrappedDuration, bond: WrappedBalance },
+    /// Several actions executed in order, internal ones only once every external one succeeded.
+    MultiAction { actions: Vec<ProposalKind> },
+    ChangeGracePeriod { grace_period: WrappedDuration }
+}
+
+/// Defines `ProposalKind::name` and the list of all kind names from the same list of variants,
+/// the match in `name` fails to compile if a variant is missing.
+macro_rules! kind_names {
+    ($($kind:ident),* $(,)?) => {
+        const KIND_NAMES: &[&str] = &[$(stringify!($kind)),*];
+
+        impl ProposalKind {
+            /// Name of the kind, same as its JSON `type` tag.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(ProposalKind::$kind { .. } => stringify!($kind),)*
+                }
+            }
+        }
+    };
+}
+
+kind_names!(
+    NewCouncil,
+    RemoveCouncil,
+    Payout,
+    ChangeVotePeriod,
+    ChangeBond,
+    ChangePolicy,
+    ChangePurpose,
+    ResoluteMarket,
+    ChangeProtocolAddress,
+    SetTokenWhitelist,
+    AddTokenWhitelist,
+    SetGov,
+    PauseProtocol,
+    UnpauseProtocol,
+    ChangeInactivityPolicy,
+    ChangeCouncilBounds,
+    ChangeCouncilStake,
+    SlashMember,
+    ChangeVotingMode,
+    ChangeStaking,
+    FtPayout,
+    CreateStream,
+    CancelStream,
+    GrantBudget,
+    RevokeBudget,
+    SetTimelock,
+    SetGuardians,
+    SetKindGracePeriod,
+    ChangeExecutionWindow,
+    SetSecretVoting,
+    ChangeReporterBond,
+    ChangeDisputeSettings,
+    MultiAction,
+    ChangeGracePeriod,
+);
+
+/// Outcome of a single action of a `MultiAction` proposal.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ActionResult {
+    Pending,
+    Executed,
+    Failed,
+}
+
+impl ProposalKind {
+    /// Whether executing the proposal calls another contract.
+    pub fn is_external(&self) -> bool {
+        match self {
+            ProposalKind::ResoluteMarket { .. }
+            | ProposalKind::SetTokenWhitelist { .. }
+            | ProposalKind::AddTokenWhitelist { .. }
+            | ProposalKind::SetGov { .. }
+            | ProposalKind::PauseProtocol { .. }
+            | ProposalKind::UnpauseProtocol { .. }
+            | ProposalKind::FtPayout { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Whether `name` is the name of a proposal kind, for settings that refer to kinds by name.
+    pub fn is_kind_name(name: &str) -> bool {
+        KIND_NAMES.contains(&name)
+    }
+
+    /// Actions executed by the proposal, the contained ones for `MultiAction`.
+    pub fn actions(&self) -> Vec<&ProposalKind> {
+        match self {
+            ProposalKind::MultiAction { actions } => actions.iter().collect(),
+            _ => vec![self],
+        }
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
@@ -40,13 +176,62 @@ pub enum ProposalKind {
 pub struct Proposal {
     pub status: ProposalStatus,
     pub proposer: AccountId,
+    /// Bond deposited by the proposer, returned once the proposal is finished.
+    pub bond: WrappedBalance,
+    /// Set for resolutions proposed by a non-council reporter, paid on top of the bond if the
+    /// proposal succeeds. A reporter's bond is only slashed if the vote or a dispute rejects the
+    /// resolution, on a veto or expiry it's handled like any other bond.
+    pub reporter_reward: Option<WrappedBalance>,
+    /// Set once a passed market resolution is disputed, votes are then counted with `DISPUTE_POLICY`.
+    pub dispute: Option<Dispute>,
     pub description: String,
     pub kind: ProposalKind,
     pub last_vote: Duration,
     pub vote_period_end: Duration,
+    /// When the outcome was decided, the grace period before execution starts from here.
+    pub decided_at: Option<Duration>,
     pub vote_yes: u64,
     pub vote_no: u64,
     pub votes: HashMap<AccountId, Vote>,
+    /// End of the reveal window if the proposal uses commit-reveal voting.
+    pub reveal_period_end: Option<Duration>,
+    /// Vote commitments, a commitment only counts once it's revealed into `votes`.
+    pub commits: HashMap<AccountId, String>,
+    pub voting_mode: VotingMode,
+    /// Total staked governance tokens at proposal creation, used for token weighted thresholds.
+    pub total_weight: WrappedBalance,
+    /// Only stake held at this time counts towards token weighted votes.
+    pub snapshot_at: WrappedTimestamp,
+    pub weight_yes: WrappedBalance,
+    pub weight_no: WrappedBalance,
+    /// Result of each action of a `MultiAction` proposal, empty until it is executed.
+    pub action_results: Vec<ActionResult>,
+    pub depends_on: Vec<U64>,
+    pub link: Option<String>,
+    pub content_hash: Option<String>,
+    /// Previous versions of the proposal, oldest first.
+    pub amendments: Vec<Amendment>,
+    /// Whether an external call of the proposal is in flight, it can't be executed again until it returns.
+    pub executing: bool,
+}
+
+/// Version of a proposal replaced by `amend_proposal`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Amendment {
+    pub description: String,
+    pub kind: ProposalKind,
+    pub depends_on: Vec<U64>,
+    pub link: Option<String>,
+    pub content_hash: Option<String>,
+    pub amended_at: WrappedTimestamp,
+}
+
+impl ProposalInput {
+    /// Bytes of off-chain metadata stored with the proposal.
+    pub fn metadata_len(&self) -> u64 {
+        (self.link.as_ref().map_or(0, |link| link.len()) + self.content_hash.as_ref().map_or(0, |hash| hash.len())) as u64
+    }
 }
 
 impl Proposal {
@@ -69,4 +254,17 @@ impl Proposal {
             ProposalStatus::Reject
         }
     }
+
+    /// Compute new vote status of a token weighted proposal given current timestamp.
+    pub fn weighted_vote_status(&self, policy: &PolicyItem) -> ProposalStatus {
+        let needed_weight = policy.weight_threshold(self.total_weight.0);
+
+        if self.total_weight.0 > 0 && self.weight_yes.0 >= needed_weight {
+            ProposalStatus::Success
+        } else if env::block_timestamp() < self.vote_period_end {
+            ProposalStatus::Vote
+        } else {
+            ProposalStatus::Reject
+        }
+    }
 }
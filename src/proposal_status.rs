@@ -1,7 +1,9 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Deserialize, Serialize};
 
-#[derive(BorshSerialize, BorshDeserialize, Eq, PartialEq, Debug, Serialize, Deserialize, Clone)]
+use crate::types::{ WrappedTimestamp };
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub enum ProposalStatus {
     /// Proposal is in active voting stage.
@@ -13,12 +15,27 @@ pub enum ProposalStatus {
     /// Proposal is finalized
     Finalized,
     /// Proposal is rejected
-    Rejected
+    Rejected,
+    /// Proposal has passed and can be executed after the timelock ends at `eta`.
+    Queued { eta: WrappedTimestamp },
+    /// Proposal was vetoed by a guardian while queued.
+    Vetoed,
+    /// Proposal passed but wasn't executed within the execution window.
+    Expired
 
 }
 
 impl ProposalStatus {
     pub fn is_finished(&self) -> bool {
-        self == &ProposalStatus::Rejected || self == &ProposalStatus::Finalized
+        self == &ProposalStatus::Rejected || self == &ProposalStatus::Finalized || self == &ProposalStatus::Vetoed
+            || self == &ProposalStatus::Expired
+    }
+
+    /// Whether the proposal passed and can be executed.
+    pub fn is_approved(&self) -> bool {
+        match self {
+            ProposalStatus::Success | ProposalStatus::Queued { .. } => true,
+            _ => false,
+        }
     }
 }
\ No newline at end of file
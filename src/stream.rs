@@ -0,0 +1,49 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{ AccountId, Balance };
+
+use crate::types::{ Timestamp, WrappedBalance, WrappedTimestamp };
+
+/// Payout vesting linearly from `start` to `end`, withdrawable by the recipient after `cliff`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Stream {
+    pub recipient: AccountId,
+    /// Token contract for fungible token streams, NEAR if `None`.
+    pub token: Option<AccountId>,
+    pub total: WrappedBalance,
+    pub start: WrappedTimestamp,
+    pub end: WrappedTimestamp,
+    pub cliff: WrappedTimestamp,
+    pub withdrawn: WrappedBalance,
+    /// Vesting stops at this timestamp if the stream was cancelled.
+    pub cancelled_at: Option<WrappedTimestamp>,
+}
+
+impl Stream {
+    /// Amount vested at the given timestamp.
+    pub fn vested(&self, timestamp: Timestamp) -> Balance {
+        let timestamp = match self.cancelled_at {
+            Some(cancelled_at) => std::cmp::min(timestamp, cancelled_at.0),
+            None => timestamp,
+        };
+        if timestamp < self.cliff.0 {
+            0
+        } else if timestamp >= self.end.0 {
+            self.total.0
+        } else {
+            let elapsed = (timestamp - self.start.0) as Balance;
+            let duration = (self.end.0 - self.start.0) as Balance;
+            self.total.0 / duration * elapsed + self.total.0 % duration * elapsed / duration
+        }
+    }
+}
+
+/// Vested and withdrawn amounts of a stream.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StreamBalance {
+    pub vested: WrappedBalance,
+    pub withdrawn: WrappedBalance,
+    pub withdrawable: WrappedBalance,
+}
@@ -0,0 +1,20 @@
+use near_sdk::serde::{Deserialize, Serialize};
+
+use crate::types::{ WrappedBalance };
+
+/// Breakdown of the NEAR balance held by the DAO.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TreasurySummary {
+    pub balance: WrappedBalance,
+    /// Balance required to cover the contract's storage staking.
+    pub storage_locked: WrappedBalance,
+    /// Bonds of proposals that are not finalized yet.
+    pub bonds_locked: WrappedBalance,
+    /// Council membership stakes, including stakes in cooldown.
+    pub stakes_locked: WrappedBalance,
+    /// NEAR committed to streams and not withdrawn yet.
+    pub streams_locked: WrappedBalance,
+    /// Balance that can be paid out by proposals.
+    pub spendable: WrappedBalance,
+}
@@ -2,6 +2,7 @@ use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Deserialize, Serialize};
 
 use near_sdk::json_types::{U64, U128};
+use near_sdk::AccountId;
 
 /// Raw type for duration in nanoseconds
 pub type Duration = u64;
@@ -18,13 +19,32 @@ pub type WrappedTimestamp = U64;
 /// Balance wrapped into a struct for JSON serialization as a string.
 pub type WrappedBalance = U128;
 
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub enum Vote {
     Yes,
     No,
 }
 
+/// Outcome of one vote of a batch, `error` is set if the vote wasn't cast.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VoteResult {
+    pub id: U64,
+    pub error: Option<String>,
+}
+
+/// How votes on a proposal are counted.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "type")]
+pub enum VotingMode {
+    /// One vote per council member.
+    Council,
+    /// Votes are weighted by the voter's stake of a NEP-141 governance token in the DAO.
+    TokenWeighted { token_id: AccountId },
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 #[serde(untagged)]
@@ -1,5 +1,9 @@
 use crate::policy_item::{ PolicyItem };
-use near_sdk::{ env, Balance };
+use crate::types::{ Vote };
+use near_sdk::{ env, AccountId, Balance };
+use near_sdk::json_types::{ Base58PublicKey };
+use ed25519_dalek::{ PublicKey, Signature, Verifier };
+use std::convert::TryFrom;
 
 pub (crate) fn to_yocto(value: u128) -> u128 {
     value * 10_u128.pow(24)
@@ -12,4 +16,41 @@ pub(crate) fn assert_self() {
         env::current_account_id(),
         "Method is private"
     );
-}
\ No newline at end of file
+}
+
+/// Hex encoded sha256 of `"<id>:<account>:<vote>:<salt>"`, committed to by `commit_vote`.
+/// The proposal id and voter are included so a published commitment can't be reused by others.
+pub(crate) fn vote_commitment(id: u64, account_id: &AccountId, vote: Vote, salt: &str) -> String {
+    let vote = match vote {
+        Vote::Yes => "Yes",
+        Vote::No => "No",
+    };
+    env::sha256(format!("{}:{}:{}:{}", id, account_id, vote, salt).as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub(crate) fn is_sha256_hex(hash: &str) -> bool {
+    hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// `Base58PublicKey` stores the curve type in the first byte, 0 for ed25519.
+pub(crate) fn is_ed25519_key(public_key: &Base58PublicKey) -> bool {
+    public_key.0.len() == 33 && public_key.0[0] == 0
+}
+
+pub(crate) fn verify_ed25519(public_key: &Base58PublicKey, message: &[u8], signature: &[u8]) -> bool {
+    if !is_ed25519_key(public_key) {
+        return false;
+    }
+    let public_key = match PublicKey::from_bytes(&public_key.0[1..]) {
+        Ok(public_key) => public_key,
+        Err(_) => return false,
+    };
+    let signature = match Signature::try_from(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    public_key.verify(message, &signature).is_ok()
+}
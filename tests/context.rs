@@ -149,6 +149,9 @@ fn test_new_proposal() {
     let proposal = ProposalInput {
         description: description(),
         kind: ProposalKind::NewCouncil{ target: c3.account_id()},
+        depends_on: vec![],
+        link: None,
+        content_hash: None,
     };
 
     let res = call!(
@@ -179,6 +182,9 @@ fn test_cross_contract_resolution() {
             market_id: U64(0),
             payout_numerator: None
         },
+        depends_on: vec![],
+        link: None,
+        content_hash: None,
     };
 
     let proposal_id: U64 = call!(
@@ -220,6 +226,9 @@ fn test_cross_contract_resolution_underlying_fail() {
             market_id: U64(1),
             payout_numerator: None
         },
+        depends_on: vec![],
+        link: None,
+        content_hash: None,
     };
 
     let proposal_id: U64 = call!(
@@ -259,6 +268,9 @@ fn test_cross_contract_set_whitelist() {
         kind: ProposalKind::SetTokenWhitelist{
             whitelist: vec![alice(), bob()]
         },
+        depends_on: vec![],
+        link: None,
+        content_hash: None,
     };
 
     let proposal_id: U64 = call!(
@@ -291,6 +303,9 @@ fn test_cross_contract_add_to_whitelist() {
         kind: ProposalKind::AddTokenWhitelist{
             to_add: bob()
         },
+        depends_on: vec![],
+        link: None,
+        content_hash: None,
     };
 
     let proposal_id: U64 = call!(
@@ -324,6 +339,9 @@ fn test_cross_contract_set_gov() {
         kind: ProposalKind::SetGov{
             new_gov: bob()
         },
+        depends_on: vec![],
+        link: None,
+        content_hash: None,
     };
 
     let proposal_id: U64 = call!(